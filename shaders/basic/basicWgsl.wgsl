@@ -0,0 +1,22 @@
+// basic.frag written in WGSL
+
+[[block]]
+struct GeneralUniforms {
+    mouse: vec2<f32>;
+    resolution: vec2<f32>;
+    time: f32;
+    mouse_down: i32;
+};
+
+[[group(0), binding(0)]] var<uniform> general: GeneralUniforms;
+
+[[location(0)]] var<in> uv: vec2<f32>;
+[[location(0)]] var<out> frag_color: vec4<f32>;
+
+[[stage(fragment)]]
+fn main() {
+    const aspect: f32 = general.resolution.x / general.resolution.y;
+    const st: vec2<f32> = vec2<f32>(uv.x * aspect, uv.y);
+    const color: vec3<f32> = vec3<f32>(st * 0.5 + vec2<f32>(0.5, 0.5), abs(sin(general.time)));
+    frag_color = vec4<f32>(color, 1.0);
+}
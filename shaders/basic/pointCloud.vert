@@ -0,0 +1,29 @@
+#version 450
+
+// vertexshaderart style: positions are generated from the vertex index
+
+layout(location = 0) out vec4 v_color;
+
+layout(set = 0, binding = 0) uniform GeneralUniforms {
+    vec2 mouse;
+    vec2 resolution;
+    float time;
+    int mouse_down;
+};
+
+#define PI 3.14159265359
+#define NUM_POINTS 20000.0
+
+void main() {
+    float i = float(gl_VertexIndex);
+    float t = i / NUM_POINTS;
+
+    // a spiral that breathes over time
+    float angle = t * PI * 200.0 + time * 0.2;
+    float radius = sqrt(t) * (0.8 + 0.1 * sin(time + t * PI * 8.0));
+    vec2 p = vec2(cos(angle), sin(angle)) * radius;
+    p.x *= resolution.y / resolution.x;
+
+    gl_Position = vec4(p, 0.0, 1.0);
+    v_color = vec4(0.5 + 0.5 * cos(t * PI * 2.0 + vec3(0.0, 2.0, 4.0) + time), 1.0);
+}
@@ -0,0 +1,31 @@
+#version 450
+
+layout(location = 0) in vec2 uv;
+layout(location = 1) in vec2 st;
+layout(location = 0) out vec4 frag_color;
+
+layout(set = 0, binding = 0) uniform GeneralUniforms {
+    vec2 mouse;
+    vec2 resolution;
+    float time;
+    int mouse_down;
+};
+
+// click and drag on the canvas to move these, in pixels from the bottom left
+layout(set = 1, binding = 0) uniform PointUniforms {
+    vec2 center;
+    vec2 light;
+};
+
+void main() {
+    vec2 px = st * resolution;
+    float scale = resolution.y;
+
+    float d = distance(px, center) / scale;
+    float ring = smoothstep(0.01, 0.0, abs(d - 0.2 - 0.02 * sin(time * 2.0)));
+
+    float glow = 0.05 / (distance(px, light) / scale + 0.05);
+    vec3 color = vec3(0.1, 0.1, 0.2) + vec3(1.0, 0.8, 0.5) * glow * glow + vec3(ring);
+
+    frag_color = vec4(color, 1.0);
+}
@@ -1,10 +1,10 @@
 use nannou::prelude::*;
 use nannou::ui::prelude::*;
 use std::cell::Ref;
+use std::collections::HashMap;
 
 use crate::interface;
 use crate::programs;
-use crate::quad_2d;
 use crate::util;
 
 pub const MEDIA_DIR: &str = "media";
@@ -89,6 +89,14 @@ widget_ids! {
         fps_container,
         fps,
         isf_inputs_title,
+        isf_stage_titles[],
+        chain_title,
+        chain_stage_toggles[],
+        chain_stage_ups[],
+        defines_title,
+        define_labels[],
+        define_inputs[],
+        point_handles[],
     }
 }
 
@@ -99,11 +107,18 @@ pub struct Model {
     pub original_height: u32,
     pub original_width: u32,
     pub paused: bool,
+    /// Paused with P or `--paused`, regaining focus doesn't resume.
+    pub paused_by_user: bool,
     pub program_store: programs::ProgramStore,
+    /// The point following the mouse while the left button is held on the canvas.
+    pub dragged_point: Option<programs::CanvasPoint>,
     pub show_controls: bool,
     pub texture: wgpu::Texture,
     pub texture_reshaper: wgpu::TextureReshaper,
     pub ui: Ui,
+    pub ui_define_edits: HashMap<String, String>,
+    /// The program `ui_define_edits` belong to.
+    pub ui_define_edits_program: Option<(String, String)>,
     pub ui_show_audio_fft: bool,
     pub ui_show_color: bool,
     pub ui_show_geometry: bool,
@@ -192,10 +207,9 @@ impl Model {
             render_pass.set_bind_group(set as u32, bind_group, &[]);
         }
 
-        // render quad
-        let vertex_range = 0..quad_2d::VERTICES.len() as u32;
-        let instance_range = 0..1;
-        render_pass.draw(vertex_range, instance_range);
+        // render the quad or the program's own vertices
+        let (vertex_count, instance_count) = self.program_store.draw_counts();
+        render_pass.draw(0..vertex_count, 0..instance_count);
     }
 
     /// Encode a render pass for each pass.
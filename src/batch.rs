@@ -0,0 +1,130 @@
+use nannou::prelude::*;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::cli;
+use crate::library;
+use crate::programs;
+
+/// Run a non-interactive command and exit with its status instead of opening the app.
+pub fn model(app: &App) {
+    let options = cli::options();
+    let libraries = library::libraries(app, &options.libraries);
+
+    let code = match options.command {
+        cli::Command::List => list(&libraries),
+        cli::Command::Check => check(app, libraries, options.json),
+        _ => 0,
+    };
+
+    process::exit(code);
+}
+
+fn list(libraries: &[library::Library]) -> i32 {
+    let folders = match programs::list_programs(libraries) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    for (folder_name, programs) in folders {
+        match programs {
+            Ok(program_names) => {
+                println!("{}", folder_name);
+                for program_name in program_names {
+                    println!("    {}", program_name);
+                }
+            }
+            Err(e) => println!("{} (error)\n    {}", folder_name, e),
+        }
+    }
+
+    0
+}
+
+/// Print the human readable report of a check.
+fn print_report(reports: &[programs::CompileReport], out: &mut dyn Write) -> io::Result<()> {
+    let failed = reports.iter().filter(|r| !r.errors.is_empty());
+    for report in failed {
+        match &report.program {
+            Some(program_name) => writeln!(out, "\n{} / {}:", report.folder, program_name)?,
+            None => writeln!(out, "\n{}:", report.folder)?,
+        }
+        for error in report.errors.iter() {
+            writeln!(out, "{}", error)?;
+        }
+    }
+
+    let num_failed = reports.iter().filter(|r| !r.errors.is_empty()).count();
+    writeln!(
+        out,
+        "\n{} of {} passed, {} failed",
+        reports.len() - num_failed,
+        reports.len(),
+        num_failed
+    )
+}
+
+fn json_report(reports: &[programs::CompileReport]) -> serde_json::Value {
+    let num_failed = reports.iter().filter(|r| !r.errors.is_empty()).count();
+    serde_json::json!({
+        "passed": num_failed == 0,
+        "total": reports.len(),
+        "failed": num_failed,
+        "results": reports,
+    })
+}
+
+/// Compile and validate every program on a device without a window.
+fn check(app: &App, libraries: Vec<library::Library>, json: Option<PathBuf>) -> i32 {
+    let adapter = match app
+        .wgpu_adapters()
+        .get_or_request(wgpu::default_adapter_request_options(), app.instance())
+    {
+        Some(a) => a,
+        None => {
+            eprintln!("no GPU adapter available");
+            return 1;
+        }
+    };
+    let device_queue_pair = adapter.get_or_request_device(wgpu::default_device_descriptor());
+    let device = device_queue_pair.device();
+
+    let program_store = programs::ProgramStore::new(libraries, device, pt2(1.0, 1.0), 1);
+    let reports = match program_store.compile_all(device) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    match json {
+        // the JSON takes stdout so the report moves to stderr
+        Some(path) if path == Path::new("-") => {
+            let _ = print_report(&reports, &mut io::stderr());
+            println!("{:#}", json_report(&reports));
+        }
+        Some(path) => {
+            let _ = print_report(&reports, &mut io::stdout());
+            let contents = format!("{:#}\n", json_report(&reports));
+            if let Err(e) = fs::write(&path, contents) {
+                eprintln!("writing {}: {}", path.to_string_lossy(), e);
+                return 1;
+            }
+        }
+        None => {
+            let _ = print_report(&reports, &mut io::stdout());
+        }
+    }
+
+    if reports.iter().all(|r| r.errors.is_empty()) {
+        0
+    } else {
+        1
+    }
+}
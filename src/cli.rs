@@ -0,0 +1,181 @@
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+pub const USAGE: &str = "usage: rusty_vision [command] [options]
+
+commands:
+    run                 open the window and run programs (default)
+    list                print every folder and its programs
+    check               compile and validate every program without a window,
+                        exits with a non-zero status if any fail (alias: compile)
+
+options:
+    --library <path>    add a shader library root, may be repeated
+    --json <path>       write the check report as JSON, - prints it instead
+                        of the human readable report
+    --folder <name>     start in this folder
+    --program <name>    start with this program
+    --size <w>x<h>      window size in pixels, 1920x1080 by default
+    --fullscreen        start fullscreen
+    --monitor <index>   open the window on this monitor
+    --no-ui             start with the controls hidden
+    --paused            start paused
+    -h, --help          print this message";
+
+/// What to do once the libraries are loaded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Run,
+    List,
+    Check,
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub command: Command,
+    pub libraries: Vec<PathBuf>,
+    pub json: Option<PathBuf>,
+    pub folder: Option<String>,
+    pub program: Option<String>,
+    pub size: Option<[u32; 2]>,
+    pub fullscreen: bool,
+    pub monitor: Option<usize>,
+    pub no_ui: bool,
+    pub paused: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            command: Command::Run,
+            libraries: vec![],
+            json: None,
+            folder: None,
+            program: None,
+            size: None,
+            fullscreen: false,
+            monitor: None,
+            no_ui: false,
+            paused: false,
+        }
+    }
+}
+
+fn parse_size(value: &str) -> Result<[u32; 2], String> {
+    let mut parts = value.split('x').map(|p| p.parse::<u32>());
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(width)), Some(Ok(height)), None) if width > 0 && height > 0 => Ok([width, height]),
+        _ => Err(format!(
+            "Invalid size '{}', expected <width>x<height>",
+            value
+        )),
+    }
+}
+
+/// Parse the arguments following the binary name.
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut command = None;
+    let mut help = false;
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {}", flag))
+        };
+
+        match arg.as_str() {
+            "--library" => options.libraries.push(PathBuf::from(value(&arg)?)),
+            "--json" => options.json = Some(PathBuf::from(value(&arg)?)),
+            "--folder" => options.folder = Some(value(&arg)?),
+            "--program" => options.program = Some(value(&arg)?),
+            "--size" => options.size = Some(parse_size(&value(&arg)?)?),
+            "--fullscreen" => options.fullscreen = true,
+            "--monitor" => {
+                let monitor = value(&arg)?;
+                options.monitor = match monitor.parse() {
+                    Ok(m) => Some(m),
+                    Err(_) => return Err(format!("Invalid monitor index '{}'", monitor)),
+                };
+            }
+            "--no-ui" => options.no_ui = true,
+            "--paused" => options.paused = true,
+            "-h" | "--help" => help = true,
+            _ if arg.starts_with('-') => return Err(format!("Unknown option '{}'", arg)),
+            _ if command.is_some() => return Err(format!("Unexpected argument '{}'", arg)),
+            "run" => command = Some(Command::Run),
+            "list" => command = Some(Command::List),
+            "check" | "compile" => command = Some(Command::Check),
+            _ => return Err(format!("Unknown command '{}'", arg)),
+        }
+    }
+
+    options.command = if help {
+        Command::Help
+    } else {
+        command.unwrap_or(Command::Run)
+    };
+    Ok(options)
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse() {
+    let args = |s: &str| {
+        s.split_whitespace()
+            .map(String::from)
+            .collect::<Vec<String>>()
+    };
+
+    assert_eq!(parse(args("")), Ok(Options::default()));
+
+    let options = parse(args(
+        "--library a --library b --folder f --program p --size 640x480 --fullscreen --monitor 1 --no-ui --paused",
+    ))
+    .unwrap();
+    assert_eq!(options.command, Command::Run);
+    assert_eq!(
+        options.libraries,
+        vec![PathBuf::from("a"), PathBuf::from("b")]
+    );
+    assert_eq!(options.folder.as_deref(), Some("f"));
+    assert_eq!(options.program.as_deref(), Some("p"));
+    assert_eq!(options.size, Some([640, 480]));
+    assert!(options.fullscreen && options.no_ui && options.paused);
+    assert_eq!(options.monitor, Some(1));
+
+    let options = parse(args("check --json -")).unwrap();
+    assert_eq!(options.command, Command::Check);
+    assert_eq!(options.json, Some(PathBuf::from("-")));
+    assert_eq!(
+        parse(args("compile --library a")).unwrap().command,
+        Command::Check
+    );
+    assert_eq!(parse(args("list")).unwrap().command, Command::List);
+    assert_eq!(parse(args("list --help")).unwrap().command, Command::Help);
+    assert_eq!(parse(args("-h list")).unwrap().command, Command::Help);
+
+    assert!(parse(args("--size 640")).is_err());
+    assert!(parse(args("--folder")).is_err());
+    assert!(parse(args("--bogus")).is_err());
+    assert!(parse(args("list compile")).is_err());
+    assert!(parse(args("render")).is_err());
+}
+
+/// The options the app was started with, exits with the usage on bad arguments or `--help`.
+pub fn options() -> Options {
+    match parse(env::args().skip(1)) {
+        Ok(options) if options.command == Command::Help => {
+            println!("{}", USAGE);
+            process::exit(0);
+        }
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use nannou::prelude::*;
+use nannou::ui::prelude::*;
+
+use crate::app;
+use crate::interface::components;
+use crate::programs::ProgramStore;
+
+pub fn height(model: &mut app::Model) -> f32 {
+    match &model.program_store.isf_chain {
+        Some(chain) => 30.0 + chain.stages().len() as f32 * 30.0,
+        None => 0.0,
+    }
+}
+
+/// Make sure there are enough widget ids for the stages of the running chain.
+/// Call before the widgets are set.
+pub fn generate_widget_ids(model: &mut app::Model) {
+    let num_stages = model
+        .program_store
+        .isf_chain
+        .as_ref()
+        .map_or(0, |c| c.stages().len());
+    let num_linked = model.program_store.isf_stage_count();
+
+    let mut id_generator = model.ui.widget_id_generator();
+    model
+        .widget_ids
+        .chain_stage_toggles
+        .resize(num_stages, &mut id_generator);
+    model
+        .widget_ids
+        .chain_stage_ups
+        .resize(num_stages, &mut id_generator);
+    model
+        .widget_ids
+        .isf_stage_titles
+        .resize(num_linked, &mut id_generator);
+}
+
+/// Section update, each stage can be turned off or moved up the chain.
+pub fn update(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    widget_ids: &app::WidgetIds,
+    ui: &mut UiCell,
+    program_store: &mut ProgramStore,
+) {
+    let chain = match &program_store.isf_chain {
+        Some(c) => c,
+        None => return,
+    };
+
+    components::label("Chain")
+        .parent(widget_ids.controls_wrapper)
+        .down(20.0)
+        .align_left_of(widget_ids.controls_wrapper)
+        .set(widget_ids.chain_title, ui);
+
+    let mut toggled = None;
+    let mut moved_up = None;
+
+    for (i, stage) in chain.stages().iter().enumerate() {
+        let (toggle_id, up_id) = match (
+            widget_ids.chain_stage_toggles.get(i),
+            widget_ids.chain_stage_ups.get(i),
+        ) {
+            (Some(t), Some(u)) => (*t, *u),
+            _ => continue,
+        };
+
+        for _click in components::button_small_wide(stage.enabled)
+            .w(165.0)
+            .parent(widget_ids.controls_wrapper)
+            .down(10.0)
+            .align_left_of(widget_ids.controls_wrapper)
+            .label(&stage.name)
+            .set(toggle_id, ui)
+        {
+            toggled = Some(i);
+        }
+
+        for _click in components::button_small(false)
+            .parent(widget_ids.controls_wrapper)
+            .right(5.0)
+            .label("up")
+            .label_rgb(1.0, 1.0, 1.0)
+            .label_font_size(10)
+            .set(up_id, ui)
+        {
+            moved_up = Some(i);
+        }
+    }
+
+    if let Some(index) = toggled {
+        program_store.toggle_isf_stage(device, encoder, index);
+    }
+    if let Some(index) = moved_up {
+        program_store.move_isf_stage_up(device, encoder, index);
+    }
+}
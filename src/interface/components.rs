@@ -189,3 +189,12 @@ pub fn a_4d_slider(val: f32, min: f32, max: f32) -> widget::Slider<'static, f32>
         .right(10.0)
         .label("A")
 }
+
+pub fn text_box(text: &str) -> widget::TextBox<'_> {
+    widget::TextBox::new(text)
+        .w_h(200.0, 27.0)
+        .font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .text_color(color::WHITE)
+        .border(0.0)
+}
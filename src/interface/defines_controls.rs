@@ -0,0 +1,96 @@
+use nannou::ui::prelude::*;
+use std::collections::HashMap;
+
+use crate::app;
+use crate::interface::components;
+use crate::programs::ProgramStore;
+
+pub fn height(model: &mut app::Model) -> f32 {
+    match model.program_store.get_defines() {
+        Some(defines) if !defines.is_empty() => 30.0 + defines.len() as f32 * 55.0,
+        _ => 0.0,
+    }
+}
+
+/// Make sure there are enough widget ids for the current program's defines.
+/// Call before the widgets are set.
+pub fn generate_widget_ids(model: &mut app::Model) {
+    let num_defines = model
+        .program_store
+        .get_defines()
+        .map(|d| d.len())
+        .unwrap_or(0);
+
+    let mut id_generator = model.ui.widget_id_generator();
+    model
+        .widget_ids
+        .define_labels
+        .resize(num_defines, &mut id_generator);
+    model
+        .widget_ids
+        .define_inputs
+        .resize(num_defines, &mut id_generator);
+}
+
+/// Section update, defines layout and update logic of the section.
+/// Edits are kept in `edits` until enter is pressed, then the program is recompiled.
+pub fn update(
+    widget_ids: &app::WidgetIds,
+    ui: &mut UiCell,
+    program_store: &mut ProgramStore,
+    edits: &mut HashMap<String, String>,
+) {
+    let defines = match program_store.get_defines() {
+        Some(d) if !d.is_empty() => d,
+        _ => return,
+    };
+
+    let mut names = defines.keys().cloned().collect::<Vec<String>>();
+    names.sort();
+    let values = names
+        .iter()
+        .map(|name| defines[name].clone())
+        .collect::<Vec<String>>();
+
+    components::label("Defines")
+        .parent(widget_ids.controls_wrapper)
+        .down(20.0)
+        .align_left_of(widget_ids.controls_wrapper)
+        .set(widget_ids.defines_title, ui);
+
+    for (i, (name, value)) in names.iter().zip(values.iter()).enumerate() {
+        let (label_id, input_id) = match (
+            widget_ids.define_labels.get(i),
+            widget_ids.define_inputs.get(i),
+        ) {
+            (Some(l), Some(t)) => (*l, *t),
+            _ => continue,
+        };
+
+        components::text_small(name)
+            .parent(widget_ids.controls_wrapper)
+            .down(10.0)
+            .align_left_of(widget_ids.controls_wrapper)
+            .set(label_id, ui);
+
+        let text = edits.get(name).unwrap_or(value).clone();
+        for event in components::text_box(&text)
+            .parent(widget_ids.controls_wrapper)
+            .down(5.0)
+            .set(input_id, ui)
+        {
+            match event {
+                widget::text_box::Event::Update(new_text) => {
+                    edits.insert(name.clone(), new_text);
+                }
+                widget::text_box::Event::Enter => {
+                    if let Some(new_value) = edits.remove(name) {
+                        if new_value != *value {
+                            program_store.set_define(name, new_value);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
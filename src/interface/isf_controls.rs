@@ -3,15 +3,27 @@ use nannou::ui::prelude::*;
 
 use crate::app;
 use crate::interface::components;
+use crate::programs::isf::chain;
 use crate::programs::isf::data;
 use crate::programs::isf::IsfPipeline;
 
 pub fn height(model: &mut app::Model) -> f32 {
-    let isf = match &model.program_store.isf_pipeline {
-        Some(isf_pipeline) => match &isf_pipeline.isf {
-            Some(isf) => isf,
-            None => return 0.0,
-        },
+    let num_linked = model.program_store.isf_stage_count();
+    let isf_pipeline = match &mut model.program_store.isf_pipeline {
+        Some(p) => p,
+        None => return 0.0,
+    };
+
+    let mut height = 0.0;
+    chain::for_each_linked(isf_pipeline, num_linked, &mut |_, stage| {
+        height += stage_height(stage);
+    });
+    height
+}
+
+fn stage_height(isf_pipeline: &IsfPipeline) -> f32 {
+    let isf = match &isf_pipeline.isf {
+        Some(isf) => isf,
         None => return 0.0,
     };
 
@@ -39,30 +51,38 @@ pub fn height(model: &mut app::Model) -> f32 {
     height
 }
 
+/// The fixed entries of an image input's source menu, followed by the media images and the
+/// folder's ISF programs.
+const IMAGE_SOURCE_LABELS: [&str; 3] = ["choose image...", "choose video...", "webcam"];
+
+/// Draws the ISF input controls under a heading.
+/// The `inputImage` of a chain stage that's `fed` by the one before it can't be changed.
+/// Returns the image input whose source was changed and its new source.
 pub fn update(
-    device: &wgpu::Device,
-    encoder: &mut wgpu::CommandEncoder,
     widget_ids: &app::WidgetIds,
     ui: &mut UiCell,
     isf_pipeline: &mut IsfPipeline,
     size: Point2,
-) {
-    let isf = match &isf_pipeline.isf {
-        Some(isf) => isf,
-        None => return,
-    };
+    title: &str,
+    title_id: widget::Id,
+    fed: bool,
+) -> Option<(String, data::ImageChoice)> {
+    let isf = isf_pipeline.isf.as_ref()?;
 
     if isf.inputs.is_empty() {
-        return;
+        return None;
     }
 
+    let mut image_selection = None;
+
     if let Some(isf_widget_ids) = &isf_pipeline.widget_ids.as_ref() {
-        widget::Text::new("ISF Inputs")
+        widget::Text::new(title)
             .rgb(0.9, 0.9, 0.9)
             .font_size(18)
             .parent(widget_ids.controls_wrapper)
             .down(10.0)
-            .set(widget_ids.isf_inputs_title, ui);
+            .align_left_of(widget_ids.controls_wrapper)
+            .set(title_id, ui);
 
         let data_inputs = isf_pipeline.isf_data.inputs_mut();
 
@@ -163,40 +183,67 @@ pub fn update(
                     let mut label_name = input.name.clone();
                     label_name.push_str("-label");
 
+                    if fed && input.name == chain::CHAIN_INPUT {
+                        components::label(&format!("{}: previous stage", input.name))
+                            .align_left_of(widget_ids.controls_wrapper)
+                            .set(*isf_widget_ids.get(&label_name).unwrap(), ui);
+                        continue;
+                    }
+
                     components::label(input.name.as_str())
                         .align_left_of(widget_ids.controls_wrapper)
                         .set(*isf_widget_ids.get(&label_name).unwrap(), ui);
 
-                    let labels = &["image", "video", "webcam"];
+                    let options = &isf_pipeline.image_options;
+                    let program_labels = options
+                        .programs
+                        .iter()
+                        .map(|name| format!("program: {}", name))
+                        .collect::<Vec<String>>();
+                    let labels = IMAGE_SOURCE_LABELS
+                        .iter()
+                        .cloned()
+                        .chain(options.images.iter().map(|(label, _)| label.as_str()))
+                        .chain(program_labels.iter().map(|label| label.as_str()))
+                        .collect::<Vec<&str>>();
+
+                    let first_image = IMAGE_SOURCE_LABELS.len();
+                    let first_program = first_image + options.images.len();
                     let selected = match &image_input.source {
-                        data::ImageSource::Image(_) => 0,
+                        data::ImageSource::Image(path, _) => options
+                            .images
+                            .iter()
+                            .position(|(_, p)| p == path)
+                            .map_or(0, |i| first_image + i),
                         data::ImageSource::Video(_) => 1,
                         data::ImageSource::Webcam(_) => 2,
+                        data::ImageSource::Program(output) => options
+                            .programs
+                            .iter()
+                            .position(|name| *name == output.name)
+                            .map_or(0, |i| first_program + i),
                         _ => 0,
                     };
 
-                    if let Some(index) = components::drop_down(labels, selected)
+                    if let Some(index) = components::drop_down(&labels[..], selected)
                         .parent(widget_ids.controls_wrapper)
                         .down(5.0)
                         .set(*widget_id, ui)
                     {
-                        match labels[index] {
-                            "image" => {
-                                image_input.select_image(
-                                    device,
-                                    encoder,
-                                    &isf_pipeline.image_loader,
-                                );
-                            }
-                            "video" => {
-                                image_input.select_video(device);
-                            }
-                            "webcam" => {
-                                image_input.start_webcam(device, size);
-                            }
-                            _ => {}
+                        let choice = match index {
+                            0 => data::pick_image_file().map(data::ImageChoice::Image),
+                            1 => data::pick_video_file().map(data::ImageChoice::Video),
+                            2 => Some(data::ImageChoice::Webcam),
+                            i if i < first_program => Some(data::ImageChoice::Image(
+                                options.images[i - first_image].1.clone(),
+                            )),
+                            i => Some(data::ImageChoice::Program(
+                                options.programs[i - first_program].clone(),
+                            )),
                         };
-                        isf_pipeline.updated = true;
+                        if let Some(choice) = choice {
+                            image_selection = Some((input.name.clone(), choice));
+                        }
                     }
                 }
                 (data::IsfInputData::Point2d(val), isf::InputType::Point2d(input_config)) => {
@@ -205,18 +252,42 @@ pub fn update(
 
                     let mut label_name = input.name.clone();
                     label_name.push_str("-label");
+                    let mut mouse_name = input.name.clone();
+                    mouse_name.push_str("-mouse");
                     let mut x_name = input.name.clone();
                     x_name.push_str("-x");
                     let mut y_name = input.name.clone();
                     y_name.push_str("-y");
 
+                    let label_id = *isf_widget_ids.get(&label_name).unwrap();
                     components::label(input.name.as_str())
                         .align_left_of(widget_ids.controls_wrapper)
                         .parent(widget_ids.controls_wrapper)
-                        .set(*isf_widget_ids.get(&label_name).unwrap(), ui);
+                        .set(label_id, ui);
+
+                    // bound points follow the mouse while it's dragged on the canvas
+                    let bound = isf_pipeline.mouse_points.contains(&input.name);
+                    for _click in components::button_small(bound)
+                        .w(45.0)
+                        .parent(widget_ids.controls_wrapper)
+                        .align_right_of(widget_ids.controls_wrapper)
+                        .align_middle_y_of(label_id)
+                        .label("mouse")
+                        .label_rgb(1.0, 1.0, 1.0)
+                        .label_font_size(10)
+                        .set(*isf_widget_ids.get(&mouse_name).unwrap(), ui)
+                    {
+                        if bound {
+                            isf_pipeline.mouse_points.remove(&input.name);
+                        } else {
+                            isf_pipeline.mouse_points.insert(input.name.clone());
+                        }
+                    }
 
                     if let Some(value) = components::x_2d_slider(val[0], min[0], max[0])
                         .parent(widget_ids.controls_wrapper)
+                        .down_from(label_id, 5.0)
+                        .align_left_of(widget_ids.controls_wrapper)
                         .set(*isf_widget_ids.get(&x_name).unwrap(), ui)
                     {
                         val[0] = value;
@@ -287,4 +358,6 @@ pub fn update(
             };
         }
     }
+
+    image_selection
 }
@@ -3,24 +3,28 @@ use nannou::ui::prelude::*;
 use nannou::ui::DrawToFrameError;
 
 use crate::app;
+use crate::programs;
 
 mod audio_fft_controls;
 mod camera_info;
+mod chain_controls;
 mod color_controls;
 mod components;
+mod defines_controls;
 mod errors;
 mod geometry_controls;
 mod image_controls;
 mod isf_controls;
 mod noise_controls;
+mod point_handles;
 mod video_controls;
 
 fn controls_height(model: &mut app::Model) -> f32 {
-    let mut height = 140.0;
+    let mut height = 140.0 + defines_controls::height(model);
 
     let subscriptions = match &model.program_store.current_subscriptions {
         Some(s) => s,
-        None => return height + isf_controls::height(model),
+        None => return height + chain_controls::height(model) + isf_controls::height(model),
     };
 
     [
@@ -68,15 +72,23 @@ pub fn update(
         return;
     }
 
+    let num_linked = model.program_store.isf_stage_count();
     if let Some(isf_pipeline) = &mut model.program_store.isf_pipeline {
-        match isf_pipeline.widget_ids {
-            Some(_) => (),
-            None => {
-                isf_pipeline.generate_widget_ids(&mut model.ui);
+        let ui = &mut model.ui;
+        programs::isf::chain::for_each_linked(isf_pipeline, num_linked, &mut |_, stage| {
+            if stage.widget_ids.is_none() {
+                stage.generate_widget_ids(ui);
             }
-        };
+        });
     }
 
+    defines_controls::generate_widget_ids(model);
+    chain_controls::generate_widget_ids(model);
+
+    let canvas_points = model.program_store.canvas_points();
+    let canvas_size = model.program_store.canvas_size();
+    point_handles::generate_widget_ids(model, canvas_points.len());
+
     let ui = &mut model.ui.set_widgets();
     /////////////////////////
     // controls container
@@ -105,10 +117,11 @@ pub fn update(
         components::label("Folder")
             .parent(model.widget_ids.controls_wrapper)
             .set(model.widget_ids.current_folder_label, ui);
-        let names = folder_names
+        let labels = folder_names
             .iter()
-            .map(|s| s.as_str())
-            .collect::<Vec<&str>>();
+            .map(|name| model.program_store.folder_label(name))
+            .collect::<Vec<String>>();
+        let names = labels.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
         if let Some(selected) = components::drop_down(&names[..], model.program_store.folder_index)
             .parent(model.widget_ids.controls_wrapper)
             .down(5.0)
@@ -152,6 +165,21 @@ pub fn update(
         }
     }
 
+    /////////////////////////
+    // preprocessor defines
+    // edits that weren't entered don't carry over to another program
+    let running_program = model.program_store.running_program();
+    if running_program != model.ui_define_edits_program {
+        model.ui_define_edits.clear();
+        model.ui_define_edits_program = running_program;
+    }
+    defines_controls::update(
+        &model.widget_ids,
+        ui,
+        &mut model.program_store,
+        &mut model.ui_define_edits,
+    );
+
     if let Some(subscriptions) = &model.program_store.current_subscriptions {
         //////////////////////////////////////////////////
         // Color Controls
@@ -308,20 +336,68 @@ pub fn update(
                 &mut model.program_store.buffer_store.camera_uniforms,
             );
         }
-    } else if let Some(isf_pipeline) = &mut model.program_store.isf_pipeline {
+    } else if model.program_store.isf_pipeline.is_some() {
         //////////////////////////////////////////////////
         // ISF UI
         //////////////////////////////////////////////////
-        isf_controls::update(
+        chain_controls::update(
             device,
             encoder,
             &model.widget_ids,
             ui,
-            isf_pipeline,
-            model.size,
+            &mut model.program_store,
         );
+
+        // each stage of a chain gets its own heading
+        let stage_names = match &model.program_store.isf_chain {
+            Some(chain) => chain
+                .linked_names()
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<String>>(),
+            None => vec![String::from("ISF Inputs")],
+        };
+        let num_linked = model.program_store.isf_stage_count();
+        let chained = model.program_store.isf_chain.is_some();
+        let widget_ids = &model.widget_ids;
+        let size = model.size;
+        let mut image_selection = None;
+
+        if let Some(isf_pipeline) = &mut model.program_store.isf_pipeline {
+            programs::isf::chain::for_each_linked(isf_pipeline, num_linked, &mut |i, stage| {
+                let title_id = match (chained, widget_ids.isf_stage_titles.get(i)) {
+                    (false, _) => widget_ids.isf_inputs_title,
+                    (true, Some(id)) => *id,
+                    (true, None) => return,
+                };
+                let title = stage_names.get(i).map_or("ISF Inputs", |n| n.as_str());
+                if let Some(selection) =
+                    isf_controls::update(widget_ids, ui, stage, size, title, title_id, i > 0)
+                {
+                    image_selection = Some((i, selection));
+                }
+            });
+        }
+
+        if let Some((stage, (input_name, choice))) = image_selection {
+            model
+                .program_store
+                .set_isf_image(device, encoder, stage, &input_name, choice);
+        }
     }
 
+    //////////////////////////////////////////////////
+    // Point Handles
+    //////////////////////////////////////////////////
+    let window_rect = app.window(model.main_window_id).unwrap().rect();
+    point_handles::update(
+        &model.widget_ids,
+        ui,
+        window_rect,
+        canvas_size,
+        &canvas_points,
+    );
+
     components::container([80.0, 35.0])
         .no_parent()
         .bottom_right_with_margin(10.0)
@@ -0,0 +1,38 @@
+use nannou::prelude::*;
+use nannou::ui::prelude::*;
+
+use crate::app;
+use crate::programs::CanvasPoint;
+use crate::util;
+
+const HANDLE_RADIUS: f64 = 8.0;
+
+/// Make sure there's a handle for each point that can be dragged on the canvas.
+/// Call before the widgets are set.
+pub fn generate_widget_ids(model: &mut app::Model, num_points: usize) {
+    let mut id_generator = model.ui.widget_id_generator();
+    model
+        .widget_ids
+        .point_handles
+        .resize(num_points, &mut id_generator);
+}
+
+/// Draw a ring around each point on the canvas.
+/// The rings are graphics for the window so clicking them drags the point.
+pub fn update(
+    widget_ids: &app::WidgetIds,
+    ui: &mut UiCell,
+    window_rect: Rect,
+    canvas_size: Point2,
+    points: &[(CanvasPoint, Point2)],
+) {
+    for ((_, point), id) in points.iter().zip(widget_ids.point_handles.iter()) {
+        let position = util::canvas_to_window(window_rect, canvas_size, *point);
+        widget::Circle::outline(HANDLE_RADIUS)
+            .x_y(position.x as f64, position.y as f64)
+            .rgb(1.0, 1.0, 1.0)
+            .parent(ui.window)
+            .graphics_for(ui.window)
+            .set(*id, ui);
+    }
+}
@@ -0,0 +1,150 @@
+use nannou::prelude::*;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::app;
+use crate::util;
+
+/// Colon separated (semicolon on Windows) list of library roots.
+pub const LIBRARIES_VAR: &str = "RUSTY_VISION_LIBRARIES";
+
+/// Where ISF shaders are conventionally installed on Linux.
+#[cfg(target_os = "linux")]
+const SYSTEM_ISF_DIRS: [&str; 2] = ["/usr/share/ISF", "/usr/local/share/ISF"];
+
+/// A directory of shaders along with the media its programs use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Library {
+    /// Shown in front of the library's folders in the folder menu.
+    pub name: String,
+    pub shaders_path: PathBuf,
+    pub media_path: PathBuf,
+}
+
+impl Library {
+    /// A library root holds its media in a `media` directory inside it.
+    pub fn new(shaders_path: PathBuf) -> Self {
+        let name = shaders_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| shaders_path.to_string_lossy().to_string());
+        let media_path = shaders_path.join(app::MEDIA_DIR);
+
+        Self {
+            name,
+            shaders_path,
+            media_path,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct UserConfig {
+    libraries: Option<Vec<PathBuf>>,
+}
+
+/// `$XDG_CONFIG_HOME/rusty-vision`, or `~/.config/rusty-vision`.
+pub fn user_config_dir() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("rusty-vision"))
+}
+
+/// `config.json` in the user config directory.
+pub fn user_config_path() -> Option<PathBuf> {
+    Some(user_config_dir()?.join("config.json"))
+}
+
+fn user_config_roots() -> Vec<PathBuf> {
+    let path = match user_config_path() {
+        Some(p) if p.exists() => p,
+        _ => return vec![],
+    };
+
+    let parsed = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| serde_json::from_str::<UserConfig>(&s).map_err(|e| e.to_string()));
+    match parsed {
+        Ok(config) => config.libraries.unwrap_or_default(),
+        Err(e) => {
+            println!("ignoring {}: {}", path.to_string_lossy(), e);
+            vec![]
+        }
+    }
+}
+
+fn env_roots() -> Vec<PathBuf> {
+    match env::var_os(LIBRARIES_VAR) {
+        Some(paths) => env::split_paths(&paths).collect(),
+        None => vec![],
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn system_roots() -> Vec<PathBuf> {
+    let mut roots = SYSTEM_ISF_DIRS
+        .iter()
+        .map(PathBuf::from)
+        .collect::<Vec<PathBuf>>();
+    let data_dir = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+    if let Some(data_dir) = data_dir {
+        roots.push(data_dir.join("ISF"));
+    }
+    roots
+}
+
+#[cfg(not(target_os = "linux"))]
+fn system_roots() -> Vec<PathBuf> {
+    vec![]
+}
+
+/// Every library to load, the first one is the primary library.
+/// Roots come from the command line, the environment and then the user config file,
+/// falling back on the bundled shaders. Installed ISF directories are added after them.
+pub fn libraries(app: &App, cli_roots: &[PathBuf]) -> Vec<Library> {
+    let mut roots = cli_roots.to_vec();
+    roots.extend(env_roots());
+    roots.extend(user_config_roots());
+
+    let mut libraries: Vec<Library> = vec![];
+    add_roots(&mut libraries, roots);
+
+    if libraries.is_empty() {
+        libraries.push(Library {
+            name: "shaders".to_string(),
+            shaders_path: util::shaders_path(app),
+            media_path: util::media_path(app),
+        });
+    }
+
+    let system = system_roots().into_iter().filter(|p| p.is_dir()).collect();
+    add_roots(&mut libraries, system);
+
+    libraries
+}
+
+fn add_roots(libraries: &mut Vec<Library>, roots: Vec<PathBuf>) {
+    for root in roots {
+        if !root.is_dir() {
+            println!("skipping missing library {}", root.to_string_lossy());
+            continue;
+        }
+
+        let root = root.canonicalize().unwrap_or(root);
+        if libraries.iter().any(|l| l.shaders_path == root) {
+            continue;
+        }
+
+        // folders are grouped by library name so it has to be unique
+        let mut library = Library::new(root);
+        if libraries.iter().any(|l| l.name == library.name) {
+            library.name = library.shaders_path.to_string_lossy().to_string();
+        }
+        libraries.push(library);
+    }
+}
@@ -1,25 +1,47 @@
 use nannou::prelude::*;
+use nannou::winit::window::Fullscreen;
+use std::collections::HashMap;
 use std::{thread, time};
 
 mod app;
+mod batch;
+mod cli;
 mod interface;
+mod library;
 mod programs;
 mod quad_2d;
 mod util;
 
 fn main() {
-    nannou::app(model).update(update).run();
+    match cli::options().command {
+        cli::Command::Run => nannou::app(model).update(update).exit(exit).run(),
+        _ => nannou::app(batch::model).run(),
+    }
 }
 
 /// App setup
 fn model(app: &App) -> app::Model {
+    let options = cli::options();
+
+    let monitor = options.monitor.and_then(|index| {
+        let monitor = app.available_monitors().into_iter().nth(index);
+        if monitor.is_none() {
+            println!("monitor {} not found, using the default", index);
+        }
+        monitor
+    });
+
     // create window
-    let main_window_id = app
-        .new_window()
-        .size(1920, 1080)
+    let [window_width, window_height] = options.size.unwrap_or([1920, 1080]);
+    let mut window_builder = app.new_window().size(window_width, window_height);
+    if options.fullscreen {
+        let monitor = monitor.clone().or_else(|| app.primary_monitor());
+        window_builder = window_builder.fullscreen_with(Some(Fullscreen::Borderless(monitor)));
+    }
+    let main_window_id = window_builder
         .key_pressed(key_pressed)
         .unfocused(pause)
-        .focused(unpause)
+        .focused(focused)
         .resizable(true)
         .resized(resized)
         .mouse_moved(mouse_moved)
@@ -29,6 +51,12 @@ fn model(app: &App) -> app::Model {
         .build()
         .unwrap();
     let window = app.window(main_window_id).unwrap();
+    if !options.fullscreen {
+        if let Some(monitor) = &monitor {
+            let position = monitor.position();
+            window.set_outer_position_pixels(position.x, position.y);
+        }
+    }
     let device = window.swap_chain_device();
     let msaa_samples = window.msaa_samples();
     println!("num msaa samples: {:?}", msaa_samples);
@@ -40,8 +68,27 @@ fn model(app: &App) -> app::Model {
 
     let (width, height) = window.inner_size_pixels();
     let size = pt2(width as f32, height as f32);
-    let mut program_store = programs::ProgramStore::new(app, device, size, msaa_samples);
+    let libraries = library::libraries(app, &options.libraries);
+    for library in libraries.iter() {
+        println!(
+            "library '{}': {}",
+            library.name,
+            library.shaders_path.to_string_lossy()
+        );
+    }
+    let mut program_store = programs::ProgramStore::new(libraries, device, size, msaa_samples);
     program_store.configure(app, device, &mut encoder, msaa_samples, size);
+    if let Err(e) = program_store.select_by_name(
+        app,
+        device,
+        &mut encoder,
+        options.folder.as_deref(),
+        options.program.as_deref(),
+        size,
+        msaa_samples,
+    ) {
+        println!("{}", e);
+    }
     let vertex_buffer = quad_2d::create_vertex_buffer(device);
 
     let texture = util::create_app_texture(device, size, msaa_samples);
@@ -53,17 +100,21 @@ fn model(app: &App) -> app::Model {
     let mut ui = app.new_ui().build().unwrap();
     let widget_ids = app::WidgetIds::new(ui.widget_id_generator());
 
-    app::Model {
+    let mut model = app::Model {
         widget_ids,
         main_window_id,
         original_height: height,
         original_width: width,
         paused: false,
+        paused_by_user: options.paused,
         program_store,
-        show_controls: true,
+        dragged_point: None,
+        show_controls: !options.no_ui,
         texture,
         texture_reshaper,
         ui,
+        ui_define_edits: HashMap::new(),
+        ui_define_edits_program: None,
         ui_show_audio_fft: false,
         ui_show_color: false,
         ui_show_geometry: false,
@@ -73,7 +124,18 @@ fn model(app: &App) -> app::Model {
         resized: false,
         size,
         vertex_buffer,
+    };
+
+    if options.paused {
+        pause(app, &mut model);
     }
+
+    model
+}
+
+/// Keep the ISF input values for the next launch.
+fn exit(_app: &App, mut model: app::Model) {
+    model.program_store.save_isf_values();
 }
 
 fn resize(app: &App, model: &mut app::Model, width: u32, height: u32) {
@@ -91,6 +153,13 @@ fn unpause(_app: &App, model: &mut app::Model) {
     model.program_store.unpause();
 }
 
+/// Resume when the window gets focus again, unless paused on purpose.
+fn focused(app: &App, model: &mut app::Model) {
+    if !model.paused_by_user {
+        unpause(app, model);
+    }
+}
+
 /// Handle key pressed event
 fn key_pressed(app: &App, model: &mut app::Model, key: Key) {
     match key {
@@ -103,8 +172,10 @@ fn key_pressed(app: &App, model: &mut app::Model, key: Key) {
         Key::Key0 => resize(app, model, model.original_width, model.original_height),
         Key::P => {
             if model.paused {
+                model.paused_by_user = false;
                 unpause(app, model);
             } else {
+                model.paused_by_user = true;
                 pause(app, model);
             }
         }
@@ -155,30 +226,73 @@ fn resized(_app: &App, model: &mut app::Model, size: Vector2<f32>) {
     model.resized = true;
 }
 
-fn mouse_moved(_app: &App, model: &mut app::Model, position: Vector2<f32>) {
+/// Whether the mouse is over the controls rather than the canvas.
+fn mouse_over_controls(model: &app::Model) -> bool {
+    if !model.show_controls {
+        return false;
+    }
+
+    match model.ui.global_input().current.widget_under_mouse {
+        Some(id) => id != model.ui.window,
+        None => false,
+    }
+}
+
+/// Move the dragged point to the mouse.
+fn drag_point(app: &App, model: &mut app::Model, position: Vector2<f32>) {
+    let point = match &model.dragged_point {
+        Some(p) => p.clone(),
+        None => return,
+    };
+
+    let rect = app.window(model.main_window_id).unwrap().rect();
+    let canvas_size = model.program_store.canvas_size();
+    let canvas_position = util::window_to_canvas(rect, canvas_size, position);
+    model
+        .program_store
+        .set_canvas_point(&point, canvas_position);
+}
+
+fn mouse_moved(app: &App, model: &mut app::Model, position: Vector2<f32>) {
     model
         .program_store
         .buffer_store
         .general_uniforms
         .set_mouse(position);
+
+    drag_point(app, model, position);
 }
 
-fn mouse_pressed(_app: &App, model: &mut app::Model, _: nannou::event::MouseButton) {
+fn mouse_pressed(app: &App, model: &mut app::Model, button: nannou::event::MouseButton) {
     model
         .program_store
         .buffer_store
         .general_uniforms
         .data
         .mouse_down = 1;
+
+    // pick up the closest point to the click and drag it
+    if button == nannou::event::MouseButton::Left && !mouse_over_controls(model) {
+        let position = app.mouse.position();
+        let rect = app.window(model.main_window_id).unwrap().rect();
+        let canvas_size = model.program_store.canvas_size();
+        let canvas_position = util::window_to_canvas(rect, canvas_size, position);
+        model.dragged_point = model.program_store.nearest_canvas_point(canvas_position);
+        drag_point(app, model, position);
+    }
 }
 
-fn mouse_released(_app: &App, model: &mut app::Model, _: nannou::event::MouseButton) {
+fn mouse_released(_app: &App, model: &mut app::Model, button: nannou::event::MouseButton) {
     model
         .program_store
         .buffer_store
         .general_uniforms
         .data
         .mouse_down = 0;
+
+    if button == nannou::event::MouseButton::Left {
+        model.dragged_point = None;
+    }
 }
 
 fn update(app: &App, model: &mut app::Model, update: Update) {
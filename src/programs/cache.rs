@@ -0,0 +1,205 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use threadpool::ThreadPool;
+
+use crate::programs::compat;
+use crate::programs::isf;
+use crate::programs::program;
+use crate::programs::shaders;
+
+/// The number of compiled programs kept around after switching away from them.
+pub const DEFAULT_CAPACITY: usize = 8;
+
+/// The number of SPIR-V binaries kept before the cache is flushed.
+const SPIRV_CAPACITY: usize = 256;
+
+/// Compiled SPIR-V keyed by a hash of the complete shader source.
+/// Shared with the background compiler so prewarmed code is picked up by whichever
+/// program ends up compiling the same source.
+pub type SpirvCache = Arc<Mutex<HashMap<u64, Vec<u8>>>>;
+
+pub fn new_spirv_cache() -> SpirvCache {
+    Arc::new(Mutex::new(HashMap::new()))
+}
+
+fn source_hash(kind: &str, source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    kind.hash(&mut hasher);
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Fetch previously compiled SPIR-V for the given source.
+pub fn get_spirv(spirv_cache: &SpirvCache, kind: &str, source: &str) -> Option<Vec<u8>> {
+    let cache = spirv_cache.lock().ok()?;
+    cache.get(&source_hash(kind, source)).cloned()
+}
+
+/// Store compiled SPIR-V for the given source.
+pub fn insert_spirv(spirv_cache: &SpirvCache, kind: &str, source: &str, bytes: &[u8]) {
+    if let Ok(mut cache) = spirv_cache.lock() {
+        if cache.len() >= SPIRV_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(source_hash(kind, source), bytes.to_vec());
+    }
+}
+
+/// Identifies a program in the library.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProgramKey {
+    pub folder: String,
+    pub program: String,
+}
+
+impl ProgramKey {
+    pub fn new(folder: String, program: String) -> Self {
+        Self { folder, program }
+    }
+}
+
+/// A compiled program that is not currently running.
+pub enum CachedProgram {
+    Program(program::Program),
+    /// The last stage of a chain holds the linked stages, the chain holds the rest.
+    Isf(isf::IsfPipeline, Option<isf::chain::IsfChain>),
+}
+
+impl CachedProgram {
+    fn dependencies(&self) -> Vec<PathBuf> {
+        match self {
+            CachedProgram::Program(program) => program.dependencies(),
+            CachedProgram::Isf(isf_pipeline, isf_chain) => {
+                let mut dependencies = isf_pipeline.dependencies();
+                dependencies.extend(isf_chain.iter().flat_map(|c| c.dependencies()));
+                dependencies
+            }
+        }
+    }
+}
+
+/// A shader to compile to SPIR-V ahead of time.
+pub enum PrewarmJob {
+    Glsl {
+        shaders_path: PathBuf,
+        filename: String,
+        frontend: Option<compat::Frontend>,
+        defines: HashMap<String, String>,
+    },
+    Isf {
+        path: PathBuf,
+        vs_path: Option<PathBuf>,
+        imports_path: PathBuf,
+        defines: HashMap<String, String>,
+    },
+}
+
+/// Keeps recently used programs compiled so switching back to them is instant.
+/// Entries are ordered from least to most recently used.
+pub struct ProgramCache {
+    capacity: usize,
+    entries: Vec<(ProgramKey, CachedProgram)>,
+    spirv_cache: SpirvCache,
+    threadpool: ThreadPool,
+}
+
+impl ProgramCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: vec![],
+            spirv_cache: new_spirv_cache(),
+            threadpool: ThreadPool::default(),
+        }
+    }
+
+    pub fn spirv_cache(&self) -> SpirvCache {
+        self.spirv_cache.clone()
+    }
+
+    /// Remove a program from the cache, handing ownership back to the caller.
+    pub fn take(&mut self, key: &ProgramKey) -> Option<CachedProgram> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    /// Store a program, evicting the least recently used one if the cache is full.
+    pub fn insert(&mut self, key: ProgramKey, program: CachedProgram) {
+        self.entries.retain(|(k, _)| *k != key);
+        self.entries.push((key, program));
+
+        while self.entries.len() > self.capacity {
+            self.entries.remove(0);
+        }
+    }
+
+    /// Keep only the programs whose key matches the predicate.
+    pub fn retain<F: FnMut(&ProgramKey) -> bool>(&mut self, mut f: F) {
+        self.entries.retain(|(key, _)| f(key));
+    }
+
+    /// Drop every cached program that was built from the given file.
+    pub fn invalidate_path(&mut self, path: &Path) {
+        self.entries
+            .retain(|(_, program)| !program.dependencies().iter().any(|p| p == path));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Compile the given shaders to SPIR-V on background threads.
+    /// The results land in the SPIR-V cache and are used when the programs are selected.
+    pub fn prewarm(&self, jobs: Vec<PrewarmJob>) {
+        for job in jobs {
+            let spirv_cache = self.spirv_cache.clone();
+            self.threadpool.execute(move || match job {
+                PrewarmJob::Glsl {
+                    shaders_path,
+                    filename,
+                    frontend,
+                    defines,
+                } => {
+                    let mut compiler = shaderc::Compiler::new().unwrap();
+                    shaders::compile_spirv(
+                        &shaders_path,
+                        &filename,
+                        frontend.as_ref(),
+                        &defines,
+                        &mut compiler,
+                        &spirv_cache,
+                    )
+                    .ok();
+                }
+                PrewarmJob::Isf {
+                    path,
+                    vs_path,
+                    imports_path,
+                    defines,
+                } => {
+                    if let Some(ref vs_path) = vs_path {
+                        isf::shader::compile_isf_vertex_spirv(
+                            vs_path,
+                            &path,
+                            &imports_path,
+                            &defines,
+                            &spirv_cache,
+                        )
+                        .ok();
+                    }
+                    isf::shader::compile_isf_spirv(
+                        &path,
+                        vs_path.as_deref(),
+                        &imports_path,
+                        &defines,
+                        &spirv_cache,
+                    )
+                    .ok();
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::programs::config;
+
+pub mod sandbox;
+pub mod shadertoy;
+
+/// A shader dialect from another environment that is translated to the GLSL 450 this app compiles.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frontend {
+    Shadertoy {
+        uniforms: Vec<String>,
+        channels: Vec<String>,
+        passes: i32,
+    },
+    /// Book of Shaders and GLSL Sandbox sketches.
+    Sandbox,
+}
+
+impl Frontend {
+    pub fn from_config(config: &config::ProgramConfig) -> Option<Self> {
+        if config.shadertoy.unwrap_or(false) {
+            let channels = shadertoy::channels(&config.config);
+            let passes = shadertoy::passes(&config.config);
+            return Some(Frontend::Shadertoy {
+                uniforms: shadertoy::uniforms(&channels, passes),
+                channels,
+                passes,
+            });
+        }
+
+        if config.sandbox.unwrap_or(false) {
+            return Some(Frontend::Sandbox);
+        }
+
+        None
+    }
+
+    /// Translate a fragment shader to GLSL 450.
+    pub fn translate(&self, source: &str) -> String {
+        match self {
+            Frontend::Shadertoy {
+                uniforms,
+                channels,
+                passes,
+            } => shadertoy::translate(source, uniforms, channels, *passes),
+            Frontend::Sandbox => sandbox::translate(source),
+        }
+    }
+
+    /// The uniform groups the translated shader binds, in bind group order.
+    pub fn uniforms(&self) -> Vec<String> {
+        match self {
+            Frontend::Shadertoy { uniforms, .. } => uniforms.clone(),
+            Frontend::Sandbox => vec!["general".to_string()],
+        }
+    }
+}
+
+/// Programs written for another environment don't list their uniforms,
+/// fill them in from the frontend.
+pub fn configure(program_config: &mut config::ProgramConfig) {
+    if let Some(frontend) = Frontend::from_config(program_config) {
+        program_config.uniforms = Some(frontend.uniforms());
+    }
+}
@@ -0,0 +1,85 @@
+use regex::Regex;
+
+/// Uniforms Book of Shaders and GLSL Sandbox sketches declare themselves,
+/// and the expression over `GeneralUniforms` that replaces each.
+/// `u_mouse` is in pixels from the bottom left, GLSL Sandbox's `mouse` is normalized.
+const BUILTINS: [(&str, &str); 6] = [
+    ("u_time", "rv_general.time"),
+    ("u_resolution", "rv_general.resolution"),
+    (
+        "u_mouse",
+        "(rv_general.mouse + rv_general.resolution * 0.5)",
+    ),
+    ("time", "rv_general.time"),
+    ("resolution", "rv_general.resolution"),
+    ("mouse", "(rv_general.mouse / rv_general.resolution + 0.5)"),
+];
+
+fn uniform_regex() -> Regex {
+    Regex::new(r"^\s*uniform\s+(?:(?:lowp|mediump|highp)\s+)?\w+\s+(\w+)\s*;").unwrap()
+}
+
+/// Rewrite a desktop GLSL 1.x sketch to GLSL 450.
+/// Removed lines are left blank so errors still point at the user's line numbers.
+pub fn translate(source: &str) -> String {
+    let uniform_regex = uniform_regex();
+    let main_regex = Regex::new(r"\bvoid\s+main\s*\(").unwrap();
+    let frag_color_regex = Regex::new(r"\bgl_FragColor\b").unwrap();
+    let frag_coord_regex = Regex::new(r"\bgl_FragCoord\b").unwrap();
+
+    let mut defines = String::new();
+    let mut lines = vec![];
+
+    for line in source.lines() {
+        if line.trim_start().starts_with("#version") {
+            lines.push(String::new());
+            continue;
+        }
+
+        let builtin = uniform_regex
+            .captures(line)
+            .and_then(|c| BUILTINS.iter().find(|(name, _)| *name == &c[1]));
+        if let Some((name, value)) = builtin {
+            defines.push_str(&format!("#define {} {}\n", name, value));
+            lines.push(String::new());
+            continue;
+        }
+
+        let line = main_regex.replace_all(line, "void rv_main(");
+        let line = frag_color_regex.replace_all(&line, "rv_frag_color");
+        let line = frag_coord_regex.replace_all(&line, "rv_frag_coord");
+        lines.push(line.into_owned());
+    }
+
+    format!(
+        "#version 450
+
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 rv_frag_color;
+
+layout(set = 0, binding = 0) uniform GeneralUniforms {{
+    vec2 mouse;
+    vec2 resolution;
+    float time;
+    int mouse_down;
+}} rv_general;
+
+{defines}#define texture2D texture
+
+vec4 rv_frag_coord;
+
+void rv_main();
+
+#line 1
+{source}
+
+void main() {{
+    rv_frag_coord = vec4(gl_FragCoord.x, rv_general.resolution.y - gl_FragCoord.y, gl_FragCoord.zw);
+    rv_frag_color = vec4(0.0, 0.0, 0.0, 1.0);
+    rv_main();
+}}
+",
+        defines = defines,
+        source = lines.join("\n")
+    )
+}
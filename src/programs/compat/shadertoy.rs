@@ -0,0 +1,170 @@
+use crate::programs::config;
+use crate::programs::uniforms::shadertoy::NUM_CHANNELS;
+
+/// The uniform group that provides the texture for a channel source.
+/// Sources are `image1`, `image2`, `video`, `webcam`, `audio`, `audio_fft` and `pass1` - `passN`.
+fn channel_group(channel: &str) -> Option<&'static str> {
+    match channel {
+        "image1" | "image2" => Some("image"),
+        "video" => Some("video"),
+        "webcam" => Some("webcam"),
+        "audio" => Some("audio"),
+        "audio_fft" => Some("audio_fft"),
+        c if c.starts_with("pass") => Some("multipass"),
+        _ => None,
+    }
+}
+
+/// The names of the textures a uniform group binds after its sampler, in binding order.
+fn group_textures(group: &str, passes: i32) -> Vec<String> {
+    match group {
+        "image" => vec!["image1".to_string(), "image2".to_string()],
+        "video" | "webcam" | "audio" | "audio_fft" => vec![group.to_string()],
+        "multipass" => (1..=passes).map(|i| format!("pass{}", i)).collect(),
+        _ => vec![],
+    }
+}
+
+pub fn channels(settings: &Option<config::ProgramSettings>) -> Vec<String> {
+    settings
+        .as_ref()
+        .and_then(|s| s.channels.clone())
+        .unwrap_or_default()
+}
+
+pub fn passes(settings: &Option<config::ProgramSettings>) -> i32 {
+    settings.as_ref().and_then(|s| s.passes).unwrap_or(0)
+}
+
+/// The uniform groups a Shadertoy program needs, in bind group order.
+pub fn uniforms(channels: &[String], passes: i32) -> Vec<String> {
+    let mut uniforms = vec!["shadertoy".to_string()];
+
+    let mut groups = channels
+        .iter()
+        .take(NUM_CHANNELS)
+        .filter_map(|c| channel_group(c))
+        .collect::<Vec<&str>>();
+    if passes > 0 {
+        groups.push("multipass");
+    }
+
+    for group in groups {
+        if !uniforms.iter().any(|u| u == group) {
+            uniforms.push(group.to_string());
+        }
+    }
+
+    uniforms
+}
+
+/// Wrap a Shadertoy `mainImage` in a GLSL 450 fragment shader.
+/// Channels sample the textures of the existing uniform groups, unbound channels are black.
+pub fn translate(source: &str, uniforms: &[String], channels: &[String], passes: i32) -> String {
+    let mut declarations = String::new();
+    let mut textures = vec![];
+    let mut pass_index = "0".to_string();
+
+    for (set, group) in uniforms.iter().enumerate() {
+        if group == "shadertoy" {
+            declarations.push_str(&format!(
+                "layout(set = {set}, binding = 0) uniform sampler rv_shadertoy_sampler;
+layout(set = {set}, binding = 1) uniform texture2D rv_empty;
+layout(set = {set}, binding = 2) uniform ShadertoyUniforms {{
+    vec3 iChannelResolution[4];
+    vec4 iDate;
+    vec4 iMouse;
+    vec3 iResolution;
+    float iTime;
+    float iTimeDelta;
+    int iFrame;
+}};
+",
+                set = set
+            ));
+            continue;
+        }
+
+        let names = group_textures(group, passes);
+        declarations.push_str(&format!(
+            "layout(set = {}, binding = 0) uniform sampler rv_{}_sampler;\n",
+            set, group
+        ));
+        for (i, name) in names.iter().enumerate() {
+            declarations.push_str(&format!(
+                "layout(set = {}, binding = {}) uniform texture2D rv_{};\n",
+                set,
+                i + 1,
+                name
+            ));
+            textures.push((name.clone(), group.clone()));
+        }
+
+        if group == "multipass" {
+            declarations.push_str(&format!(
+                "layout(set = {}, binding = {}) uniform MultipassUniforms {{ int pass_index; }} rv_multipass;\n",
+                set,
+                names.len() + 1
+            ));
+            pass_index = "rv_multipass.pass_index".to_string();
+        }
+    }
+
+    let mut channel_defines = String::new();
+    for i in 0..NUM_CHANNELS {
+        let binding = channels
+            .get(i)
+            .and_then(|c| textures.iter().find(|(name, _)| name == c));
+        let sampler = match binding {
+            Some((name, group)) => format!("sampler2D(rv_{}, rv_{}_sampler)", name, group),
+            None => "sampler2D(rv_empty, rv_shadertoy_sampler)".to_string(),
+        };
+        channel_defines.push_str(&format!("#define iChannel{} {}\n", i, sampler));
+    }
+
+    format!(
+        "#version 450
+
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 rv_frag_color;
+
+{declarations}
+{channel_defines}#define iPass {pass_index}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord);
+
+void main() {{
+    vec4 color = vec4(0.0, 0.0, 0.0, 1.0);
+    mainImage(color, vec2(gl_FragCoord.x, iResolution.y - gl_FragCoord.y));
+    rv_frag_color = color;
+}}
+
+#line 1
+{source}
+",
+        declarations = declarations,
+        channel_defines = channel_defines,
+        pass_index = pass_index,
+        source = source
+    )
+}
+
+#[cfg(test)]
+#[test]
+fn test_translate() {
+    let source = "void mainImage(out vec4 fragColor, in vec2 fragCoord) {}";
+    let channels = vec!["image1".to_string(), "pass1".to_string()];
+    let uniforms = uniforms(&channels, 1);
+    assert_eq!(uniforms, vec!["shadertoy", "image", "multipass"]);
+
+    let translated = translate(source, &uniforms, &channels, 1);
+    assert!(translated.starts_with("#version 450\n"));
+    assert!(translated.contains("layout(set = 0, binding = 2) uniform ShadertoyUniforms {"));
+    assert!(translated.contains("layout(set = 1, binding = 1) uniform texture2D rv_image1;"));
+    assert!(translated.contains("layout(set = 2, binding = 1) uniform texture2D rv_pass1;"));
+    assert!(translated.contains("#define iChannel0 sampler2D(rv_image1, rv_image_sampler)"));
+    assert!(translated.contains("#define iChannel1 sampler2D(rv_pass1, rv_multipass_sampler)"));
+    assert!(translated.contains("#define iChannel2 sampler2D(rv_empty, rv_shadertoy_sampler)"));
+    assert!(translated.contains("#define iPass rv_multipass.pass_index"));
+    assert!(translated.ends_with(&format!("#line 1\n{}\n", source)));
+}
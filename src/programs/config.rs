@@ -2,22 +2,31 @@ use nannou::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
+use std::path::{Path, PathBuf};
 
-use crate::util;
+use crate::library::Library;
+use crate::programs::compat;
+use crate::programs::discovery;
+use crate::programs::validation;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PipelineConfig {
     pub vert: Option<String>,
     pub frag: String,
+    /// Draw this many vertices generated from `gl_VertexIndex` instead of the fullscreen quad.
+    pub vertex_count: Option<u32>,
+    pub topology: Option<String>,
+    pub instance_count: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProgramSettings {
     pub audio_feature_smoothing: Option<f32>,
     pub audio_fft_smoothing: Option<f32>,
     pub camera_position: Option<Vector3<f32>>,
     pub camera_target: Option<Vector3<f32>>,
     pub camera_up: Option<Vector3<f32>>,
+    pub channels: Option<Vec<String>>,
     pub color_mode: Option<u32>,
     pub shape_rotation: Option<Vector3<f32>>,
     pub image1: Option<String>,
@@ -31,22 +40,47 @@ pub struct ProgramSettings {
     pub noise_sharpen: Option<i32>,
     pub noise_speed: Option<f32>,
     pub passes: Option<i32>,
+    /// Names of the 2D points of the `points` uniforms, set by dragging on the canvas.
+    pub points: Option<Vec<String>>,
     pub video: Option<String>,
     pub video_speed: Option<f32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Where an ISF image input gets its pixels from.
+/// Paths are relative to the library's media directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IsfImageSource {
+    Image(String),
+    Video(String),
+    Webcam,
+    /// The output of another ISF program in the same folder.
+    Program(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProgramConfig {
     pub pipeline: PipelineConfig,
     pub uniforms: Option<Vec<String>>,
     pub config: Option<ProgramSettings>,
     pub isf: Option<bool>,
+    pub shadertoy: Option<bool>,
+    pub sandbox: Option<bool>,
+    pub defines: Option<HashMap<String, String>>,
+    /// The sources ISF image inputs start with, by input name.
+    pub images: Option<HashMap<String, IsfImageSource>>,
+    /// ISF filters in the same folder drawn after this program, each one reading the previous
+    /// stage through its `inputImage`.
+    pub chain: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FolderConfig {
     pub default: String,
     pub programs: HashMap<String, ProgramConfig>,
+    /// Index of the library the folder was loaded from.
+    #[serde(skip)]
+    pub library: usize,
 }
 
 impl FolderConfig {
@@ -71,21 +105,87 @@ impl FolderConfig {
 pub struct RootConfig {
     pub default: String,
     pub folders: Vec<String>,
+    pub prewarm: Option<bool>,
+    pub discover: Option<bool>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub default: String,
     pub folders: HashMap<String, FolderConfig>,
+    pub prewarm: bool,
+    /// Problems found while validating the index files.
+    pub diagnostics: Vec<validation::Diagnostic>,
+    /// Folders whose index file couldn't be read or parsed, with the error.
+    pub broken_folders: HashMap<String, String>,
 }
 
-pub fn get_config(app: &App) -> Result<Config, String> {
-    let root_path = util::shaders_path(app)
+/// Read, parse and validate a folder's index file.
+fn read_folder(
+    shaders_path: &Path,
+    folder: &str,
+) -> Result<(FolderConfig, Vec<validation::Diagnostic>), String> {
+    let path = shaders_path
+        .join(folder)
         .join("index.json")
         .into_os_string()
         .into_string()
         .unwrap();
 
+    let json_string = match fs::read_to_string(path.clone()) {
+        Ok(s) => s,
+        Err(e) => return Err(format!("Reading {}: {}", path, e.to_string())),
+    };
+
+    let mut folder_config: FolderConfig = match serde_json::from_str(json_string.as_str()) {
+        Ok(c) => c,
+        Err(e) => {
+            return Err(validation::Diagnostic::from_json_error(Path::new(&path), &e).to_string())
+        }
+    };
+
+    let diagnostics = validation::validate_folder(
+        folder,
+        Path::new(&path),
+        &json_string,
+        &shaders_path.join(folder),
+        &folder_config,
+    );
+
+    for program_config in folder_config.programs.values_mut() {
+        compat::configure(program_config);
+    }
+
+    Ok((folder_config, diagnostics))
+}
+
+/// A library without a root index file, like a directory of ISF shaders, is only discovered.
+fn discover_library(shaders_path: &Path) -> Config {
+    let folders = discovery::discover(shaders_path);
+    let mut folder_names = folders.keys().cloned().collect::<Vec<String>>();
+    folder_names.sort();
+
+    Config {
+        default: folder_names.first().cloned().unwrap_or_default(),
+        folders,
+        prewarm: false,
+        diagnostics: vec![],
+        broken_folders: HashMap::new(),
+    }
+}
+
+/// Read a library's root index file and every folder it lists.
+fn read_library(shaders_path: &Path) -> Result<Config, String> {
+    let root_path = shaders_path
+        .join("index.json")
+        .into_os_string()
+        .into_string()
+        .unwrap();
+
+    if !Path::new(&root_path).exists() {
+        return Ok(discover_library(shaders_path));
+    }
+
     let root_json_string = match fs::read_to_string(root_path.clone()) {
         Ok(s) => s,
         Err(e) => return Err(format!("Reading {}: {}", root_path, e.to_string())),
@@ -93,52 +193,174 @@ pub fn get_config(app: &App) -> Result<Config, String> {
 
     let root_config: RootConfig = match serde_json::from_str(root_json_string.as_str()) {
         Ok(c) => c,
-        Err(e) => return Err(format!("Parsing {}: {}", root_path, e.to_string())),
+        Err(e) => {
+            return Err(
+                validation::Diagnostic::from_json_error(Path::new(&root_path), &e).to_string(),
+            )
+        }
     };
 
+    let diagnostics =
+        validation::validate_root(Path::new(&root_path), &root_json_string, &root_config);
+
     let mut config = Config {
         default: root_config.default,
         folders: HashMap::new(),
+        prewarm: root_config.prewarm.unwrap_or(false),
+        diagnostics,
+        broken_folders: HashMap::new(),
     };
 
+    // a broken folder shouldn't take the rest of the library down with it
     for folder in root_config.folders.iter() {
-        let path = util::shaders_path(app)
-            .join(folder)
-            .join("index.json")
-            .into_os_string()
-            .into_string()
-            .unwrap();
+        match read_folder(shaders_path, folder) {
+            Ok((folder_config, diagnostics)) => {
+                config.diagnostics.extend(diagnostics);
+                config.folders.insert(folder.clone(), folder_config);
+            }
+            Err(e) => {
+                config.broken_folders.insert(folder.clone(), e);
+            }
+        }
+    }
+
+    if root_config.discover.unwrap_or(false) {
+        let discovered = discovery::discover(shaders_path);
+        discovery::merge(shaders_path, &mut config.folders, discovered);
+    }
 
-        let json_string = match fs::read_to_string(path.clone()) {
-            Ok(s) => s,
-            Err(e) => return Err(format!("Reading {}: {}", path, e.to_string())),
-        };
+    Ok(config)
+}
 
-        let folder_config: FolderConfig = match serde_json::from_str(json_string.as_str()) {
-            Ok(c) => c,
-            Err(e) => return Err(format!("Parsing {}: {}", path, e.to_string())),
-        };
+/// Read every library, the first one is the primary library and supplies the defaults.
+/// A secondary library that fails to load shows up as a single broken folder.
+pub fn get_config(libraries: &[Library]) -> Result<Config, String> {
+    let mut config = read_library(&libraries[0].shaders_path)?;
 
-        config.folders.insert(folder.clone(), folder_config);
+    for (index, library) in libraries.iter().enumerate().skip(1) {
+        match read_library(&library.shaders_path) {
+            Ok(library_config) => config.add_library(index, library, library_config),
+            Err(e) => {
+                config.broken_folders.insert(library.name.clone(), e);
+            }
+        }
     }
 
     Ok(config)
 }
 
 impl Config {
+    /// Add the folders of a secondary library, grouped under the library's name.
+    /// Their shader paths are made absolute since they don't live in the primary library.
+    fn add_library(&mut self, index: usize, library: &Library, other: Config) {
+        let group = |name: &str| format!("{} / {}", library.name, name);
+
+        for (folder_name, mut folder_config) in other.folders {
+            let folder_path = library.shaders_path.join(&folder_name);
+            for program_config in folder_config.programs.values_mut() {
+                let pipeline = &mut program_config.pipeline;
+                pipeline.frag = folder_path
+                    .join(&pipeline.frag)
+                    .to_string_lossy()
+                    .to_string();
+                pipeline.vert = pipeline
+                    .vert
+                    .as_ref()
+                    .map(|vert| folder_path.join(vert).to_string_lossy().to_string());
+            }
+            folder_config.library = index;
+            self.folders.insert(group(&folder_name), folder_config);
+        }
+
+        for (folder_name, error) in other.broken_folders {
+            self.broken_folders.insert(group(&folder_name), error);
+        }
+
+        for mut diagnostic in other.diagnostics {
+            diagnostic.folder = diagnostic.folder.map(|f| group(&f));
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// The names of all folders, including broken ones.
     pub fn get_folder_names(&self) -> Vec<String> {
         let mut folder_names = vec![];
-        for (name, _) in self.folders.iter() {
-            folder_names.push(name.clone());
+        for name in self.folders.keys().chain(self.broken_folders.keys()) {
+            if !folder_names.contains(name) {
+                folder_names.push(name.clone());
+            }
         }
         folder_names.sort();
         folder_names
     }
 
+    /// Keep using the last working config of folders that broke since it was loaded.
+    pub fn keep_broken_folders(&mut self, previous: &Config) {
+        for name in self.broken_folders.keys() {
+            if let Some(folder_config) = previous.folders.get(name) {
+                self.folders.insert(name.clone(), folder_config.clone());
+                self.diagnostics.extend(
+                    previous
+                        .diagnostics
+                        .iter()
+                        .filter(|d| d.folder.as_ref() == Some(name))
+                        .cloned(),
+                );
+            }
+        }
+    }
+
+    pub fn program(&self, folder_name: &str, program_name: &str) -> Option<&ProgramConfig> {
+        self.folders.get(folder_name)?.programs.get(program_name)
+    }
+
+    /// The name shown in the folder menu, broken folders are marked.
+    pub fn folder_label(&self, name: &str) -> String {
+        match self.broken_folders.get(name) {
+            Some(_) => format!("{} (error)", name),
+            None => name.to_string(),
+        }
+    }
+
     pub fn get_default_folder_index(&self, folder_names: &[String]) -> Result<usize, String> {
         match folder_names.iter().position(|n| *n == self.default) {
             Some(i) => Ok(i),
             None => Err(format!("Invalid default folder '{}'", self.default)),
         }
     }
+
+    /// The errors found in a program's config, one per line. Warnings are only printed.
+    pub fn program_errors(&self, folder_name: &str, program_name: &str) -> Option<String> {
+        let errors = self
+            .diagnostics
+            .iter()
+            .filter(|d| {
+                d.severity == validation::Severity::Error
+                    && d.folder.as_deref() == Some(folder_name)
+                    && d.program.as_deref() == Some(program_name)
+            })
+            .map(|d| d.to_string())
+            .collect::<Vec<String>>();
+
+        if errors.is_empty() {
+            None
+        } else {
+            Some(errors.join("\n"))
+        }
+    }
+
+    /// The shader files of every configured program.
+    pub fn program_paths(&self, shaders_path: &Path) -> Vec<PathBuf> {
+        let mut paths = vec![];
+        for (folder_name, folder_config) in self.folders.iter() {
+            for program_config in folder_config.programs.values() {
+                let folder_path = shaders_path.join(folder_name);
+                paths.push(folder_path.join(&program_config.pipeline.frag));
+                if let Some(vert) = &program_config.pipeline.vert {
+                    paths.push(folder_path.join(vert));
+                }
+            }
+        }
+        paths
+    }
 }
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::programs::config::{FolderConfig, PipelineConfig, ProgramConfig};
+use crate::programs::isf::util::parse_isf;
+
+/// The folder for ISF shaders at the top of a library that don't list any categories.
+const UNCATEGORIZED: &str = "ISF";
+
+fn isf_program(frag: String) -> ProgramConfig {
+    ProgramConfig {
+        pipeline: PipelineConfig {
+            vert: None,
+            frag,
+            vertex_count: None,
+            topology: None,
+            instance_count: None,
+        },
+        uniforms: None,
+        config: None,
+        isf: Some(true),
+        shadertoy: None,
+        sandbox: None,
+        defines: None,
+        images: None,
+        chain: None,
+    }
+}
+
+/// Build folders from every `.fs` file with a valid ISF header under the shaders directory.
+/// Shaders in a directory belong to a folder named after it, shaders at the top level
+/// are grouped by their first ISF category instead.
+pub fn discover(shaders_path: &Path) -> HashMap<String, FolderConfig> {
+    let mut folders: HashMap<String, FolderConfig> = HashMap::new();
+
+    let paths = walkdir::WalkDir::new(shaders_path)
+        .into_iter()
+        .filter_map(|res| res.ok())
+        .map(|entry| entry.path().to_path_buf())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "fs"));
+
+    for path in paths {
+        let isf = match fs::read_to_string(&path).map(|s| parse_isf(&s)) {
+            Ok(Ok(isf)) => isf,
+            _ => continue,
+        };
+
+        let program_name = match path.file_stem() {
+            Some(stem) => stem.to_string_lossy().to_string(),
+            None => continue,
+        };
+
+        let relative_dir = path
+            .parent()
+            .and_then(|dir| dir.strip_prefix(shaders_path).ok())
+            .map(|dir| {
+                dir.components()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string())
+                    .collect::<Vec<String>>()
+                    .join("/")
+            })
+            .unwrap_or_default();
+
+        // shaders in their own directory are relative to it, category folders have no directory
+        let (folder_name, frag) = if relative_dir.is_empty() {
+            let category = isf
+                .categories
+                .first()
+                .cloned()
+                .unwrap_or_else(|| UNCATEGORIZED.to_string());
+            (category, path.to_string_lossy().to_string())
+        } else {
+            let filename = path.file_name().unwrap().to_string_lossy().to_string();
+            (relative_dir, filename)
+        };
+
+        let folder = folders.entry(folder_name).or_insert_with(|| FolderConfig {
+            default: program_name.clone(),
+            programs: HashMap::new(),
+            library: 0,
+        });
+        folder
+            .programs
+            .entry(program_name)
+            .or_insert_with(|| isf_program(frag));
+    }
+
+    // start on the first program alphabetically rather than whichever was found first
+    for folder in folders.values_mut() {
+        if let Some(name) = folder.get_program_names().first() {
+            folder.default = name.clone();
+        }
+    }
+
+    folders
+}
+
+/// Add discovered folders and programs to the explicit config.
+/// Explicit entries win, and a shader an explicit program already uses isn't added again.
+pub fn merge(
+    shaders_path: &Path,
+    folders: &mut HashMap<String, FolderConfig>,
+    discovered: HashMap<String, FolderConfig>,
+) {
+    for (folder_name, discovered_folder) in discovered {
+        let folder = match folders.get_mut(&folder_name) {
+            Some(f) => f,
+            None => {
+                folders.insert(folder_name, discovered_folder);
+                continue;
+            }
+        };
+
+        let folder_path = shaders_path.join(&folder_name);
+        let used = folder
+            .programs
+            .values()
+            .map(|p| folder_path.join(&p.pipeline.frag))
+            .collect::<Vec<_>>();
+
+        for (program_name, program) in discovered_folder.programs {
+            if used.contains(&folder_path.join(&program.pipeline.frag)) {
+                continue;
+            }
+            folder.programs.entry(program_name).or_insert(program);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use nannou::prelude::*;
+use std::path::PathBuf;
+
+use crate::programs::isf::data::ImageChoice;
+use crate::programs::isf::values;
+use crate::programs::isf::IsfPipeline;
+
+/// The image input a filter reads the previous stage from.
+pub const CHAIN_INPUT: &str = "inputImage";
+
+/// A program in a chain, the first one generates the image and the rest filter it.
+pub struct ChainStage {
+    pub name: String,
+    pub enabled: bool,
+    /// The source `inputImage` starts with when no stage feeds it.
+    input_choice: Option<ImageChoice>,
+    /// Only set while the stage isn't linked into the running pipeline.
+    pipeline: Option<IsfPipeline>,
+}
+
+/// A program followed by ISF filters, each drawing the previous stage into its `inputImage`.
+///
+/// The enabled stages are linked using program sources, so the last one is the pipeline drawn
+/// to the window and it holds all the others.
+pub struct IsfChain {
+    stages: Vec<ChainStage>,
+    num_samples: u32,
+}
+
+impl IsfChain {
+    /// Link the stages in order, returning the chain and its last stage.
+    pub fn new(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        stages: Vec<(String, IsfPipeline)>,
+        num_samples: u32,
+    ) -> (Self, Option<IsfPipeline>) {
+        let stages = stages
+            .into_iter()
+            .map(|(name, pipeline)| ChainStage {
+                name,
+                enabled: true,
+                input_choice: pipeline.image_choices.get(CHAIN_INPUT).cloned(),
+                pipeline: Some(pipeline),
+            })
+            .collect();
+
+        let mut chain = Self {
+            stages,
+            num_samples,
+        };
+        let last = chain.link(device, encoder);
+        (chain, last)
+    }
+
+    pub fn stages(&self) -> &[ChainStage] {
+        &self.stages
+    }
+
+    /// The number of stages linked into the running pipeline.
+    pub fn num_linked(&self) -> usize {
+        self.stages.iter().filter(|s| s.enabled).count()
+    }
+
+    /// The names of the linked stages from first to last.
+    pub fn linked_names(&self) -> Vec<&str> {
+        self.stages
+            .iter()
+            .filter(|s| s.enabled)
+            .map(|s| s.name.as_str())
+            .collect()
+    }
+
+    /// Feed each enabled stage into the next one and return the last.
+    /// Filters without an `inputImage` are disabled instead.
+    fn link(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> Option<IsfPipeline> {
+        let mut linked: Option<(String, IsfPipeline)> = None;
+
+        for stage in self.stages.iter_mut().filter(|s| s.enabled) {
+            let mut pipeline = match stage.pipeline.take() {
+                Some(p) => p,
+                None => continue,
+            };
+
+            if let Some((name, mut previous)) = linked.take() {
+                if !pipeline.has_image_input(CHAIN_INPUT) {
+                    println!(
+                        "Disabling '{}' in the chain, it has no {} for '{}'",
+                        stage.name, CHAIN_INPUT, name
+                    );
+                    pipeline.end_session();
+                    stage.enabled = false;
+                    stage.pipeline = Some(pipeline);
+                    linked = Some((name, previous));
+                    continue;
+                }
+
+                previous.set_dst_sample_count(1);
+                previous.render_offscreen(device, encoder);
+                pipeline.set_program_source(CHAIN_INPUT, &name, previous);
+            }
+
+            linked = Some((stage.name.clone(), pipeline));
+        }
+
+        linked.map(|(_, mut last)| {
+            last.set_dst_sample_count(self.num_samples);
+            last
+        })
+    }
+
+    /// Take the running pipeline apart so the stages can be rearranged.
+    fn unlink(&mut self, last: IsfPipeline) {
+        let linked = (0..self.stages.len())
+            .filter(|i| self.stages[*i].enabled)
+            .collect::<Vec<usize>>();
+
+        let mut next = Some(last);
+        for (position, index) in linked.into_iter().enumerate().rev() {
+            let mut pipeline = match next.take() {
+                Some(p) => p,
+                None => break,
+            };
+
+            // the first stage's `inputImage` isn't fed by the chain
+            if position > 0 {
+                next = pipeline.take_program_source(CHAIN_INPUT);
+                match self.stages[index].input_choice.clone() {
+                    Some(ImageChoice::Program(_)) | None => (),
+                    Some(choice) => pipeline.set_image_choice(CHAIN_INPUT, choice),
+                }
+            }
+
+            self.stages[index].pipeline = Some(pipeline);
+        }
+    }
+
+    /// Turn a stage on or off, at least one stage is always on.
+    pub fn toggle(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        last: IsfPipeline,
+        index: usize,
+    ) -> Option<IsfPipeline> {
+        self.unlink(last);
+
+        let num_linked = self.num_linked();
+        if let Some(stage) = self.stages.get_mut(index) {
+            if stage.enabled && num_linked > 1 {
+                stage.enabled = false;
+                stage.pipeline.iter_mut().for_each(|p| p.end_session());
+            } else if !stage.enabled {
+                stage.enabled = true;
+                stage.pipeline.iter_mut().for_each(|p| p.start_session());
+            }
+        }
+
+        self.link(device, encoder)
+    }
+
+    /// Move a stage one place closer to the start of the chain.
+    pub fn move_up(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        last: IsfPipeline,
+        index: usize,
+    ) -> Option<IsfPipeline> {
+        self.unlink(last);
+
+        if index > 0 && index < self.stages.len() {
+            self.stages.swap(index - 1, index);
+        }
+
+        self.link(device, encoder)
+    }
+
+    /// Record the input values of the stages that aren't linked.
+    pub fn save_values(&self, saved: &mut values::SavedValues) {
+        for pipeline in self.stages.iter().filter_map(|s| s.pipeline.as_ref()) {
+            pipeline.save_values(saved);
+        }
+    }
+
+    /// The files the stages that aren't linked are built from.
+    pub fn dependencies(&self) -> Vec<PathBuf> {
+        self.stages
+            .iter()
+            .filter_map(|s| s.pipeline.as_ref())
+            .flat_map(|p| p.dependencies())
+            .collect()
+    }
+}
+
+/// The linked stage at a position from the start of the chain, given the last of `num_linked`.
+pub fn linked_stage_mut(
+    last: &mut IsfPipeline,
+    num_linked: usize,
+    position: usize,
+) -> Option<&mut IsfPipeline> {
+    let mut pipeline = last;
+    for _ in position + 1..num_linked {
+        pipeline = pipeline.program_source_mut(CHAIN_INPUT)?;
+    }
+    Some(pipeline)
+}
+
+/// Visit the linked stages from first to last with their positions.
+pub fn for_each_linked<F>(last: &mut IsfPipeline, num_linked: usize, f: &mut F)
+where
+    F: FnMut(usize, &mut IsfPipeline),
+{
+    let position = num_linked.saturating_sub(1);
+    if position > 0 {
+        if let Some(previous) = last.program_source_mut(CHAIN_INPUT) {
+            for_each_linked(previous, position, f);
+        }
+    }
+    f(position, last);
+}
@@ -0,0 +1,328 @@
+//! Renders the `shaders/isf/test_*.fs` shaders without a window and compares them against the
+//! reference images in `shaders/isf/reference`.
+//!
+//! Only a software adapter (lavapipe, SwiftShader or WARP) is used so the renders match across
+//! machines. A missing reference is a failure, `UPDATE_ISF_REFERENCES=1` writes all of them.
+
+use nannou::image;
+use nannou::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::programs::cache;
+use crate::programs::isf::{shader, util, IsfPipeline, IsfTime};
+
+const SHADERS_PATH: &str = "shaders";
+const TESTS_PATH: &str = "shaders/isf";
+const REFERENCES_PATH: &str = "shaders/isf/reference";
+/// Every image input is given the same image so renders don't depend on the media directory.
+const INPUT_IMAGE: &str = "media/forest.jpg";
+
+const RENDER_SIZE: [u32; 2] = [128, 96];
+const FRAME_RATE: f32 = 60.0;
+const NUM_FRAMES: i32 = 31;
+/// Frames compared against references, persistent buffers need the ones in between.
+const CAPTURED_FRAMES: [i32; 3] = [0, 1, 30];
+
+/// The largest difference in a channel that's put down to the adapter.
+const CHANNEL_TOLERANCE: u8 = 3;
+/// The fraction of pixels allowed to go over `CHANNEL_TOLERANCE`.
+const MAX_DIFFERENT_PIXELS: f32 = 0.001;
+
+const IMAGE_LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The test shaders, audio input can't be rendered the same way twice.
+fn test_shaders() -> Vec<PathBuf> {
+    let mut paths = fs::read_dir(TESTS_PATH)
+        .unwrap()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.starts_with("test_") && name.ends_with(".fs")
+        })
+        .filter(|path| match util::read_isf_from_path(path) {
+            Ok(isf) => !isf.inputs.iter().any(|input| {
+                matches!(
+                    input.ty,
+                    isf::InputType::Audio(_) | isf::InputType::AudioFft(_)
+                )
+            }),
+            Err(_) => true,
+        })
+        .collect::<Vec<PathBuf>>();
+    paths.sort();
+    paths
+}
+
+/// A device on a CPU adapter, hardware adapters don't render the same as the references.
+fn request_device() -> Result<(wgpu::Device, wgpu::Queue), String> {
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let adapter = instance
+        .enumerate_adapters(wgpu::BackendBit::PRIMARY)
+        .find(|a| a.get_info().device_type == wgpu::DeviceType::Cpu)
+        .ok_or_else(|| String::from("no software wgpu adapter, install lavapipe"))?;
+
+    let descriptor = wgpu::default_device_descriptor();
+    futures::executor::block_on(adapter.request_device(&descriptor, None))
+        .map_err(|e| format!("{:?}", e))
+}
+
+fn reference_path(fs_path: &Path, frame: i32) -> PathBuf {
+    let name = fs_path.file_stem().unwrap().to_string_lossy();
+    Path::new(REFERENCES_PATH).join(format!("{}-{}.png", name, frame))
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    fs_path: &Path,
+) -> IsfPipeline {
+    let image_choices = match util::read_isf_from_path(fs_path) {
+        Ok(isf) => isf
+            .inputs
+            .iter()
+            .filter(|input| matches!(input.ty, isf::InputType::Image))
+            .map(|input| {
+                let choice = super::data::ImageChoice::Image(PathBuf::from(INPUT_IMAGE));
+                (input.name.clone(), choice)
+            })
+            .collect(),
+        Err(_) => HashMap::new(),
+    };
+
+    let frag = fs_path.file_name().unwrap().to_string_lossy();
+    IsfPipeline::new(
+        device,
+        encoder,
+        shader::vertex_path(Path::new(TESTS_PATH), &frag, None),
+        fs_path.to_path_buf(),
+        Frame::TEXTURE_FORMAT,
+        RENDER_SIZE,
+        1,
+        Path::new("media"),
+        1,
+        PathBuf::from(SHADERS_PATH),
+        HashMap::new(),
+        cache::new_spirv_cache(),
+        image_choices,
+    )
+}
+
+/// Draw the frames of a shader, returning the captured ones in order.
+fn render_frames(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    fs_path: &Path,
+) -> Result<Vec<image::RgbaImage>, String> {
+    let descriptor = wgpu::CommandEncoderDescriptor::default();
+    let mut encoder = device.create_command_encoder(&descriptor);
+    let mut pipeline = create_pipeline(device, &mut encoder, fs_path);
+    pipeline.render_offscreen(device, &mut encoder);
+    queue.submit(Some(encoder.finish()));
+
+    if let Some(errors) = pipeline.get_program_errors() {
+        let mut errors = errors.into_iter().collect::<Vec<(String, String)>>();
+        errors.sort();
+        let messages = errors.into_iter().map(|(k, v)| format!("{}: {}", k, v));
+        return Err(messages.collect::<Vec<String>>().join("\n"));
+    }
+
+    // images are loaded on a threadpool and uploaded by updates
+    let started = Instant::now();
+    loop {
+        let mut encoder = device.create_command_encoder(&descriptor);
+        pipeline.encode_update(
+            device,
+            &mut encoder,
+            Path::new("media"),
+            Vec::<PathBuf>::new(),
+            1,
+        );
+        queue.submit(Some(encoder.finish()));
+        if !pipeline.isf_data.is_loading() {
+            break;
+        }
+        if started.elapsed() > IMAGE_LOAD_TIMEOUT {
+            return Err(String::from("timed out loading images"));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let data_errors = pipeline.get_data_errors();
+    if !data_errors.is_empty() {
+        return Err(format!("{:?}", data_errors));
+    }
+
+    let capturer = wgpu::TextureCapturer::default();
+    let (tx, rx) = mpsc::channel();
+
+    for frame_index in 0..NUM_FRAMES {
+        // a fixed date so DATE doesn't change the output
+        let time_delta = if frame_index > 0 {
+            1.0 / FRAME_RATE
+        } else {
+            0.0
+        };
+        let isf_time = IsfTime {
+            time: frame_index as f32 / FRAME_RATE,
+            time_delta,
+            date: [2021.0, 1.0, 1.0, 0.0],
+            frame_index,
+            ..Default::default()
+        };
+
+        let mut encoder = device.create_command_encoder(&descriptor);
+        pipeline.encode_passes(device, &mut encoder, isf_time);
+        let snapshot = match pipeline.output_texture() {
+            Some(texture) if CAPTURED_FRAMES.contains(&frame_index) => {
+                Some(capturer.capture(device, &mut encoder, texture))
+            }
+            _ => None,
+        };
+        queue.submit(Some(encoder.finish()));
+
+        if let Some(snapshot) = snapshot {
+            let tx = tx.clone();
+            snapshot
+                .read(move |result| {
+                    let image = result.map(|mapping| {
+                        let view = mapping.as_image();
+                        let (w, h) = view.dimensions();
+                        image::RgbaImage::from_raw(w, h, view.to_vec()).unwrap()
+                    });
+                    tx.send((frame_index, image)).ok();
+                })
+                .map_err(|_| String::from("timed out reading a frame"))?;
+        }
+    }
+
+    device.poll(wgpu::Maintain::Wait);
+    capturer
+        .await_active_snapshots(device)
+        .map_err(|_| String::from("timed out reading frames"))?;
+    drop(tx);
+
+    let mut frames = rx.into_iter().collect::<Vec<_>>();
+    frames.sort_by_key(|(frame_index, _)| *frame_index);
+    frames
+        .into_iter()
+        .map(|(_, image)| image.map_err(|e| format!("{:?}", e)))
+        .collect()
+}
+
+/// Compare a render against its reference, `Err` describes how far off it is.
+fn compare_images(rendered: &image::RgbaImage, reference: &image::RgbaImage) -> Result<(), String> {
+    if rendered.dimensions() != reference.dimensions() {
+        return Err(format!(
+            "size is {:?}, expected {:?}",
+            rendered.dimensions(),
+            reference.dimensions()
+        ));
+    }
+
+    let mut num_different = 0;
+    let mut max_difference = 0;
+    for (a, b) in rendered.pixels().zip(reference.pixels()) {
+        let difference =
+            a.0.iter()
+                .zip(b.0.iter())
+                .map(|(a, b)| (*a as i16 - *b as i16).abs() as u8)
+                .max()
+                .unwrap_or(0);
+        max_difference = max_difference.max(difference);
+        if difference > CHANNEL_TOLERANCE {
+            num_different += 1;
+        }
+    }
+
+    let num_pixels = rendered.width() * rendered.height();
+    let fraction = num_different as f32 / num_pixels.max(1) as f32;
+    if fraction > MAX_DIFFERENT_PIXELS {
+        return Err(format!(
+            "{} of {} pixels differ, by up to {}",
+            num_different, num_pixels, max_difference
+        ));
+    }
+
+    Ok(())
+}
+
+/// Compare a frame against its reference, or write the reference when updating.
+fn check_frame(fs_path: &Path, frame: i32, rendered: &image::RgbaImage) -> Result<(), String> {
+    let path = reference_path(fs_path, frame);
+    let update = std::env::var("UPDATE_ISF_REFERENCES").map_or(false, |v| v == "1");
+
+    if update {
+        fs::create_dir_all(REFERENCES_PATH).map_err(|e| e.to_string())?;
+        rendered.save(&path).map_err(|e| e.to_string())?;
+        println!("wrote {}", path.to_string_lossy());
+        return Ok(());
+    }
+
+    if !path.exists() {
+        return Err(format!(
+            "missing reference {}, write it with UPDATE_ISF_REFERENCES=1",
+            path.to_string_lossy()
+        ));
+    }
+
+    let reference = image::open(&path).map_err(|e| e.to_string())?.to_rgba8();
+    compare_images(rendered, &reference)
+}
+
+#[test]
+#[ignore = "needs a software wgpu adapter and the images in shaders/isf/reference"]
+fn test_isf_conformance() {
+    let (device, queue) = request_device().unwrap();
+
+    let mut failures = vec![];
+    for fs_path in test_shaders() {
+        let name = fs_path.to_string_lossy().to_string();
+        let frames = match render_frames(&device, &queue, &fs_path) {
+            Ok(frames) => frames,
+            Err(e) => {
+                failures.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+
+        if frames.len() != CAPTURED_FRAMES.len() {
+            failures.push(format!("{}: captured {} frames", name, frames.len()));
+            continue;
+        }
+
+        for (frame, rendered) in CAPTURED_FRAMES.iter().zip(frames.iter()) {
+            if let Err(e) = check_frame(&fs_path, *frame, rendered) {
+                failures.push(format!("{} frame {}: {}", name, frame, e));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_compare_images() {
+    let reference = image::RgbaImage::from_pixel(100, 100, image::Rgba([100, 150, 200, 255]));
+
+    // differences within the channel tolerance pass
+    let mut rendered = reference.clone();
+    rendered.put_pixel(0, 0, image::Rgba([103, 147, 200, 255]));
+    assert!(compare_images(&rendered, &reference).is_ok());
+
+    // a few pixels over it pass too
+    rendered.put_pixel(1, 0, image::Rgba([0, 0, 0, 255]));
+    assert!(compare_images(&rendered, &reference).is_ok());
+
+    // but not too many
+    for x in 0..10 {
+        rendered.put_pixel(x, 1, image::Rgba([0, 0, 0, 255]));
+    }
+    assert!(compare_images(&rendered, &reference).is_err());
+
+    let smaller = image::RgbaImage::from_pixel(50, 100, image::Rgba([100, 150, 200, 255]));
+    assert!(compare_images(&smaller, &reference).is_err());
+}
@@ -4,13 +4,19 @@ use nannou::image;
 use nannou::prelude::*;
 use opencv::prelude::*;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use thiserror::Error;
 use threadpool::ThreadPool;
 use tinyfiledialogs::open_file_dialog;
 
+use crate::programs::isf::shader;
+use crate::programs::isf::util::{self, CubeImport};
+use crate::programs::isf::values::{SavedInputs, SavedValue};
+use crate::programs::isf::IsfPipeline;
 use crate::programs::uniforms::audio::AudioUniforms;
 use crate::programs::uniforms::audio_fft::AudioFftUniforms;
 use crate::programs::uniforms::audio_source::AudioSource;
@@ -44,6 +50,8 @@ pub enum ImageLoadError {
         #[from]
         err: image::ImageError,
     },
+    #[error("{0}")]
+    Missing(String),
 }
 
 pub type ImportName = String;
@@ -83,6 +91,99 @@ fn create_black_texture(
     texture
 }
 
+/// A cube map imported by the shader, the faces are the layers of the texture.
+#[derive(Debug)]
+pub struct CubeMap {
+    pub paths: Vec<PathBuf>,
+    pub texture: wgpu::Texture,
+    pub error: Option<String>,
+}
+
+fn create_cube_texture(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    size: u32,
+    data: &[u8],
+) -> wgpu::Texture {
+    let texture = wgpu::TextureBuilder::new()
+        .usage(default_isf_texture_usage())
+        .extent(wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth: 6,
+        })
+        .format(wgpu::TextureFormat::Rgba8UnormSrgb)
+        .build(device);
+    texture.upload_data(device, encoder, data);
+    texture
+}
+
+/// Read the six faces into one buffer, they must all be square and the same size.
+fn load_cube_faces(paths: &[PathBuf]) -> Result<(u32, Vec<u8>), String> {
+    if paths.len() != 6 {
+        return Err(format!("a cube map needs 6 faces, found {}", paths.len()));
+    }
+
+    let mut size = None;
+    let mut data = vec![];
+
+    for path in paths {
+        let face = image::open(path)
+            .map_err(|err| format!("{:?}: {}", path, err))?
+            .to_rgba8();
+        let (width, height) = face.dimensions();
+        if width != height {
+            return Err(format!("cube face {:?} is not square", path));
+        }
+
+        match size {
+            Some(s) if s != width => {
+                return Err(format!(
+                    "cube face {:?} is not the same size as the other faces",
+                    path
+                ));
+            }
+            _ => size = Some(width),
+        }
+
+        data.extend(face.into_raw());
+    }
+
+    Ok((size.unwrap_or(1), data))
+}
+
+impl CubeMap {
+    /// Load the faces, falling back to a black cube map if any are missing or invalid.
+    fn load(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        cube: &CubeImport,
+        shader_dir: &Path,
+        images_path: &Path,
+    ) -> Self {
+        let faces = cube
+            .paths
+            .iter()
+            .map(|path| util::imported_path(shader_dir, images_path, path))
+            .collect::<Result<Vec<PathBuf>, String>>()
+            .and_then(|paths| load_cube_faces(&paths));
+
+        let (texture, error) = match faces {
+            Ok((size, data)) => (create_cube_texture(device, encoder, size, &data), None),
+            Err(error) => (
+                create_cube_texture(device, encoder, 1, &[0u8; 24]),
+                Some(error),
+            ),
+        };
+
+        Self {
+            paths: cube.paths.clone(),
+            texture,
+            error,
+        }
+    }
+}
+
 impl ImageState {
     /// Update the image state.
     fn update(
@@ -156,23 +257,61 @@ pub struct IsfInputError {
     ty: String,
 }
 
+/// The file or device an image input was set to.
+/// It's kept so captures can be reopened when a session is restarted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageChoice {
+    Image(PathBuf),
+    Video(PathBuf),
+    Webcam,
+    /// The output of another ISF program in the same folder.
+    Program(String),
+}
+
+/// What an image input can be set to from the controls.
+#[derive(Debug, Clone, Default)]
+pub struct ImageOptions {
+    /// The images in the media directory with the names they're shown with.
+    pub images: Vec<(String, PathBuf)>,
+    /// The ISF programs in the folder.
+    pub programs: Vec<String>,
+}
+
+/// Another ISF program rendered to a texture every frame.
+pub struct ProgramOutput {
+    pub name: String,
+    pub pipeline: Box<IsfPipeline>,
+}
+
+impl fmt::Debug for ProgramOutput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProgramOutput")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
 #[derive(Debug)]
 pub enum ImageSource {
     None,
-    Image(ImageState),
+    Image(PathBuf, ImageState),
     Video(VideoCapture),
     Webcam(VideoCapture),
+    Program(ProgramOutput),
 }
 
 #[derive(Debug)]
 pub struct ImageInput {
     pub source: ImageSource,
+    pub choice: Option<ImageChoice>,
 }
 
 impl ImageInput {
-    fn new() -> Self {
+    fn new(choice: Option<ImageChoice>) -> Self {
         Self {
             source: ImageSource::None,
+            choice,
         }
     }
 
@@ -180,11 +319,43 @@ impl ImageInput {
         match &mut self.source {
             ImageSource::Video(v) => v.end_session(),
             ImageSource::Webcam(v) => v.end_session(),
+            ImageSource::Program(output) => output.pipeline.end_session(),
             _ => (),
         };
     }
 
-    pub fn load_image(
+    /// Switch to a new source, it's opened on the next update.
+    pub fn select(&mut self, choice: ImageChoice) {
+        self.end_sessions();
+        self.choice = Some(choice);
+        self.source = ImageSource::None;
+    }
+
+    /// Feed the input with the output of another program.
+    pub fn set_program(&mut self, name: &str, pipeline: IsfPipeline) {
+        self.end_sessions();
+        self.choice = Some(ImageChoice::Program(name.to_string()));
+        self.source = ImageSource::Program(ProgramOutput {
+            name: name.to_string(),
+            pipeline: Box::new(pipeline),
+        });
+    }
+
+    /// Stop feeding the input with another program, handing the program back.
+    pub fn take_program(&mut self) -> Option<IsfPipeline> {
+        match std::mem::replace(&mut self.source, ImageSource::None) {
+            ImageSource::Program(output) => {
+                self.choice = None;
+                Some(*output.pipeline)
+            }
+            source => {
+                self.source = source;
+                None
+            }
+        }
+    }
+
+    fn load_image(
         &mut self,
         device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
@@ -193,51 +364,29 @@ impl ImageInput {
     ) -> bool {
         self.end_sessions();
         let mut image_source = ImageState::None;
-        let updated = image_source.update(device, encoder, image_loader, path);
-        self.source = ImageSource::Image(image_source);
+        let updated = image_source.update(device, encoder, image_loader, path.clone());
+        self.source = ImageSource::Image(path, image_source);
         updated
     }
 
-    pub fn select_image(
-        &mut self,
-        device: &wgpu::Device,
-        encoder: &mut wgpu::CommandEncoder,
-        image_loader: &ImageLoader,
-    ) {
-        let filepath = match open_file_dialog("Load Image", "~", Some((&["*.jpg", "*.png"], ""))) {
-            Some(path) => path,
-            None => return,
-        };
-
-        println!("selected image: {:?}", filepath);
-
-        self.load_image(device, encoder, image_loader, PathBuf::from(filepath));
-    }
-
-    pub fn select_video(&mut self, device: &wgpu::Device) {
-        let filepath = match open_file_dialog(
-            "Load Video",
-            "~",
-            Some((&["*.mp4", "*.avi", "*.mov", "*.mpeg", "*.flv", "*.wmv"], "")),
-        ) {
-            Some(filepath) => filepath,
-            None => return,
-        };
-
-        println!("selected video: {:?}", filepath);
+    fn open_video(&mut self, device: &wgpu::Device, path: &Path) {
+        println!("opening video: {:?}", path);
 
         self.end_sessions();
 
-        let capture =
-            opencv::videoio::VideoCapture::from_file(&filepath, opencv::videoio::CAP_ANY).unwrap();
+        let capture = opencv::videoio::VideoCapture::from_file(
+            &path.to_string_lossy(),
+            opencv::videoio::CAP_ANY,
+        )
+        .unwrap();
 
         let video_capture = VideoCapture::new(device, capture, 1.0);
 
         self.source = ImageSource::Video(video_capture);
     }
 
-    pub fn start_webcam(&mut self, device: &wgpu::Device, size: Point2) {
-        println!("selected webcam");
+    fn start_webcam(&mut self, device: &wgpu::Device, size: Point2) {
+        println!("starting webcam");
 
         self.end_sessions();
 
@@ -254,9 +403,51 @@ impl ImageInput {
         self.source = ImageSource::Webcam(video_capture);
     }
 
+    /// Open the chosen source if it isn't open yet and update it.
+    /// Inputs without a choice, or waiting for a program, show the first image in the media
+    /// directory.
+    fn update(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        image_loader: &ImageLoader,
+        images_path: &Path,
+        size: [u32; 2],
+    ) -> bool {
+        match &mut self.source {
+            ImageSource::None => match self.choice.clone() {
+                Some(ImageChoice::Image(path)) => {
+                    self.load_image(device, encoder, image_loader, path)
+                }
+                Some(ImageChoice::Video(path)) => {
+                    self.open_video(device, &path);
+                    true
+                }
+                Some(ImageChoice::Webcam) => {
+                    self.start_webcam(device, pt2(size[0] as f32, size[1] as f32));
+                    true
+                }
+                Some(ImageChoice::Program(_)) | None => match image_paths(images_path).next() {
+                    Some(path) => self.load_image(device, encoder, image_loader, path),
+                    None => false,
+                },
+            },
+            ImageSource::Image(path, image_state) => {
+                image_state.update(device, encoder, image_loader, path.clone())
+            }
+            ImageSource::Video(ref mut video) | ImageSource::Webcam(ref mut video) => {
+                video.update();
+                video.update_texture(device, encoder);
+                false
+            }
+            // updated by the pipeline that owns the input so it sees the same file changes
+            ImageSource::Program(_) => false,
+        }
+    }
+
     pub fn get_error(&self) -> Option<IsfInputError> {
         match &self.source {
-            ImageSource::Image(image_state) => match image_state.get_error() {
+            ImageSource::Image(_, image_state) => match image_state.get_error() {
                 Some(error) => Some(IsfInputError {
                     msg: error.to_string(),
                     ty: String::from("Image"),
@@ -282,6 +473,24 @@ impl ImageInput {
     }
 }
 
+/// Ask the user for an image file.
+pub fn pick_image_file() -> Option<PathBuf> {
+    let filepath = open_file_dialog("Load Image", "~", Some((&["*.jpg", "*.png"], "")))?;
+    println!("selected image: {:?}", filepath);
+    Some(PathBuf::from(filepath))
+}
+
+/// Ask the user for a video file.
+pub fn pick_video_file() -> Option<PathBuf> {
+    let filepath = open_file_dialog(
+        "Load Video",
+        "~",
+        Some((&["*.mp4", "*.avi", "*.mov", "*.mpeg", "*.flv", "*.wmv"], "")),
+    )?;
+    println!("selected video: {:?}", filepath);
+    Some(PathBuf::from(filepath))
+}
+
 #[derive(Debug)]
 pub enum IsfInputData {
     Event { happening: bool },
@@ -296,7 +505,7 @@ pub enum IsfInputData {
 }
 
 /// Given a path to a directory, produces the paths of all images within it.
-fn image_paths(dir: &Path) -> impl Iterator<Item = PathBuf> {
+pub fn image_paths(dir: &Path) -> impl Iterator<Item = PathBuf> {
     walkdir::WalkDir::new(dir)
         .into_iter()
         .filter_map(|res| res.ok())
@@ -308,12 +517,9 @@ impl IsfInputData {
     /// Initialise a new `IsfInputData` instance.
     fn new(
         device: &wgpu::Device,
-        encoder: &mut wgpu::CommandEncoder,
-        image_loader: &ImageLoader,
-        images_path: &Path,
         audio_source: &mut AudioSource,
+        image_choices: &HashMap<InputName, ImageChoice>,
         input: &isf::Input,
-        _size: [u32; 2],
     ) -> Self {
         match &input.ty {
             isf::InputType::Event => IsfInputData::Event { happening: false },
@@ -351,12 +557,9 @@ impl IsfInputData {
                 IsfInputData::Color(lin_srgba(red, green, blue, alpha))
             }
             isf::InputType::Image => {
-                let mut image_input = ImageInput::new();
-                // mage_input.start_webcam(device, pt2(size[0] as f32, size[1] as f32));
-                if let Some(path) = image_paths(images_path).next() {
-                    image_input.load_image(device, encoder, image_loader, path);
-                }
-                IsfInputData::Image(image_input)
+                // the source is opened by `update`
+                let choice = image_choices.get(&input.name).cloned();
+                IsfInputData::Image(ImageInput::new(choice))
             }
             isf::InputType::Audio(a) => {
                 let n_samples = a.num_samples.unwrap_or(DEFAULT_AUDIO_SAMPLE_COUNT);
@@ -382,33 +585,24 @@ impl IsfInputData {
         image_loader: &ImageLoader,
         images_path: &Path,
         audio_source: &mut AudioSource,
+        image_choices: &HashMap<InputName, ImageChoice>,
         input: &isf::Input,
         size: [u32; 2],
     ) -> bool {
         match (self, &input.ty) {
             (IsfInputData::Event { .. }, isf::InputType::Event) => (),
             (IsfInputData::Bool(_), isf::InputType::Bool(_)) => (),
-            (IsfInputData::Long { .. }, isf::InputType::Long(_)) => {}
+            // keep the value if it's still one of the options
+            (IsfInputData::Long { value, selected }, isf::InputType::Long(n))
+                if n.values.is_empty() || n.values.contains(&*value) =>
+            {
+                *selected = n.values.iter().position(|v| *v == *value).unwrap_or(0);
+            }
             (IsfInputData::Float(_), isf::InputType::Float(_)) => {}
             (IsfInputData::Point2d(_), isf::InputType::Point2d(_)) => {}
             (IsfInputData::Color(_), isf::InputType::Color(_)) => {}
             (IsfInputData::Image(ref mut image_input), isf::InputType::Image) => {
-                match &mut image_input.source {
-                    ImageSource::None => {
-                        if let Some(path) = image_paths(images_path).next() {
-                            return image_input.load_image(device, encoder, image_loader, path);
-                        }
-                    }
-                    ImageSource::Image(image_state) => {
-                        if let Some(path) = image_paths(images_path).next() {
-                            return image_state.update(device, encoder, image_loader, path);
-                        }
-                    }
-                    ImageSource::Video(ref mut video) | ImageSource::Webcam(ref mut video) => {
-                        video.update();
-                        video.update_texture(device, encoder);
-                    }
-                }
+                return image_input.update(device, encoder, image_loader, images_path, size);
             }
             (IsfInputData::Audio(audio), isf::InputType::Audio(_)) => {
                 audio.update();
@@ -419,33 +613,51 @@ impl IsfInputData {
                 audio_fft.update_texture(device, encoder);
             }
             (data, _) => {
-                *data = Self::new(
+                data.end_session(audio_source);
+                *data = Self::new(device, audio_source, image_choices, input);
+                return data.update(
                     device,
                     encoder,
                     image_loader,
                     images_path,
                     audio_source,
+                    image_choices,
                     input,
                     size,
-                )
+                );
             }
         }
         false
     }
 
     fn end_session(&mut self, audio_source: &mut AudioSource) {
+        match self {
+            IsfInputData::Image(ref mut image_input) => image_input.end_sessions(),
+            IsfInputData::Audio(audio) => {
+                audio.end_session(audio_source);
+            }
+            IsfInputData::AudioFft(audio_fft) => {
+                audio_fft.end_session(audio_source);
+            }
+            _ => (),
+        }
+    }
+
+    fn start_session(&mut self, audio_source: &mut AudioSource) {
         match self {
             IsfInputData::Image(ref mut image_input) => match &mut image_input.source {
-                ImageSource::Video(ref mut video) | ImageSource::Webcam(ref mut video) => {
-                    video.end_session();
+                // captures can't be resumed once ended, they're reopened by the next update
+                ImageSource::Video(_) | ImageSource::Webcam(_) => {
+                    image_input.source = ImageSource::None;
                 }
+                ImageSource::Program(output) => output.pipeline.start_session(),
                 _ => (),
             },
             IsfInputData::Audio(audio) => {
-                audio.end_session(audio_source);
+                audio.start_session(audio_source);
             }
             IsfInputData::AudioFft(audio_fft) => {
-                audio_fft.end_session(audio_source);
+                audio_fft.start_session(audio_source);
             }
             _ => (),
         }
@@ -457,6 +669,7 @@ impl IsfInputData {
                 ImageSource::Video(ref mut video) | ImageSource::Webcam(ref mut video) => {
                     video.pause();
                 }
+                ImageSource::Program(output) => output.pipeline.pause(),
                 _ => (),
             },
             IsfInputData::Audio(audio) => {
@@ -475,6 +688,7 @@ impl IsfInputData {
                 ImageSource::Video(ref mut video) | ImageSource::Webcam(ref mut video) => {
                     video.unpause();
                 }
+                ImageSource::Program(output) => output.pipeline.unpause(),
                 _ => (),
             },
             IsfInputData::Audio(audio) => {
@@ -506,6 +720,31 @@ impl IsfInputData {
 
 pub type IsfDataInputs = HashMap<InputName, IsfInputData>;
 
+/// The format of passes with `"FLOAT": true`, for simulation state that needs full precision.
+pub const FLOAT_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+/// Copy the region two textures of the same format have in common.
+fn copy_overlap(encoder: &mut wgpu::CommandEncoder, src: &wgpu::Texture, dst: &wgpu::Texture) {
+    let [src_width, src_height] = src.size();
+    let [dst_width, dst_height] = dst.size();
+    let src_view = wgpu::TextureCopyView {
+        texture: src,
+        mip_level: 0,
+        origin: wgpu::Origin3d { x: 0, y: 0, z: 0 },
+    };
+    let dst_view = wgpu::TextureCopyView {
+        texture: dst,
+        mip_level: 0,
+        origin: wgpu::Origin3d { x: 0, y: 0, z: 0 },
+    };
+    let extent = wgpu::Extent3d {
+        width: src_width.min(dst_width),
+        height: src_height.min(dst_height),
+        depth: 1,
+    };
+    encoder.copy_texture_to_texture(src_view, dst_view, extent);
+}
+
 #[derive(Debug, Clone)]
 pub struct IsfPassTextures {
     pub uniform_texture: wgpu::Texture,
@@ -513,14 +752,22 @@ pub struct IsfPassTextures {
 }
 
 impl IsfPassTextures {
+    /// Float passes aren't multisampled since 32-bit float targets usually can't be.
     pub fn new(
         device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
         size: [u32; 2],
         num_samples: u32,
+        float: bool,
     ) -> Self {
+        let (format, num_samples) = if float {
+            (FLOAT_TEXTURE_FORMAT, 1)
+        } else {
+            (Frame::TEXTURE_FORMAT, num_samples)
+        };
+
         let render_texture = wgpu::TextureBuilder::new()
-            .format(Frame::TEXTURE_FORMAT)
+            .format(format)
             .size(size)
             .usage(
                 wgpu::TextureUsage::RENDER_ATTACHMENT
@@ -531,7 +778,7 @@ impl IsfPassTextures {
             .build(device);
 
         let uniform_texture = wgpu::TextureBuilder::new()
-            .format(Frame::TEXTURE_FORMAT)
+            .format(format)
             .size(size)
             .usage(default_isf_texture_usage())
             .sample_count(num_samples)
@@ -551,6 +798,16 @@ impl IsfPassTextures {
         self.uniform_texture.size()
     }
 
+    pub fn is_float(&self) -> bool {
+        self.uniform_texture.format() == FLOAT_TEXTURE_FORMAT
+    }
+
+    /// Carry over the contents of the textures a pass used before it was resized.
+    pub fn copy_from(&self, encoder: &mut wgpu::CommandEncoder, previous: &IsfPassTextures) {
+        copy_overlap(encoder, &previous.uniform_texture, &self.uniform_texture);
+        copy_overlap(encoder, &previous.render_texture, &self.render_texture);
+    }
+
     pub fn size_bytes(&self) -> usize {
         self.uniform_texture.size_bytes()
     }
@@ -572,6 +829,7 @@ impl IsfPassTextures {
 #[derive(Debug, Default)]
 pub struct IsfData {
     imported: HashMap<ImportName, ImageState>,
+    cube_maps: Vec<(ImportName, CubeMap)>,
     inputs: IsfDataInputs,
     passes: Vec<IsfPassTextures>,
 }
@@ -582,6 +840,11 @@ impl IsfData {
         &self.imported
     }
 
+    /// The imported cube maps in the order they're declared.
+    pub fn cube_maps(&self) -> &[(ImportName, CubeMap)] {
+        &self.cube_maps
+    }
+
     /// The map of all declared inputs.
     pub fn inputs(&self) -> &IsfDataInputs {
         &self.inputs
@@ -603,6 +866,12 @@ impl IsfData {
         });
     }
 
+    pub fn start_session(&mut self, audio_source: &mut AudioSource) {
+        self.inputs
+            .iter_mut()
+            .for_each(|(_, input)| input.start_session(audio_source));
+    }
+
     pub fn pause(&mut self, audio_source: &mut AudioSource) {
         self.inputs
             .iter_mut()
@@ -626,6 +895,19 @@ impl IsfData {
         }
     }
 
+    /// Whether any imported or input images are still being read from disk.
+    pub fn is_loading(&self) -> bool {
+        let loading = |state: &ImageState| matches!(state, ImageState::Loading(_));
+        self.imported.values().any(loading)
+            || self.inputs.values().any(|input| match input {
+                IsfInputData::Image(ImageInput {
+                    source: ImageSource::Image(_, state),
+                    ..
+                }) => loading(state),
+                _ => false,
+            })
+    }
+
     pub fn get_errors(&self) -> HashMap<String, Vec<String>> {
         let mut errors = HashMap::new();
         let image_key = String::from("Image");
@@ -637,6 +919,13 @@ impl IsfData {
             }
         });
 
+        self.cube_maps.iter().for_each(|(_, cube)| {
+            if let Some(error) = &cube.error {
+                let entry = errors.entry(image_key.clone()).or_insert_with(Vec::new);
+                entry.push(error.clone());
+            }
+        });
+
         self.inputs.iter().for_each(|(_, input)| {
             if let Some(error) = input.get_error() {
                 let entry = errors.entry(error.ty).or_insert_with(Vec::new);
@@ -647,6 +936,59 @@ impl IsfData {
         errors
     }
 
+    /// The values of the inputs that are kept between launches.
+    pub fn saved_values(&self) -> SavedInputs {
+        self.inputs
+            .iter()
+            .filter_map(|(name, input)| {
+                let value = match input {
+                    IsfInputData::Bool(val) => SavedValue::Bool(*val),
+                    IsfInputData::Long { value, .. } => SavedValue::Long(*value),
+                    IsfInputData::Float(val) => SavedValue::Float(*val),
+                    IsfInputData::Point2d(point) => SavedValue::Point2d([point.x, point.y]),
+                    IsfInputData::Color(c) => SavedValue::Color([c.red, c.green, c.blue, c.alpha]),
+                    _ => return None,
+                };
+                Some((name.clone(), value))
+            })
+            .collect()
+    }
+
+    /// Set inputs to saved values, skipping the ones whose type changed since.
+    pub fn restore_values(&mut self, isf: &isf::Isf, saved: &SavedInputs) {
+        for input in &isf.inputs {
+            let (data, saved_value) =
+                match (self.inputs.get_mut(&input.name), saved.get(&input.name)) {
+                    (Some(d), Some(v)) => (d, v),
+                    _ => continue,
+                };
+
+            match (data, saved_value, &input.ty) {
+                (IsfInputData::Bool(val), SavedValue::Bool(v), _) => *val = *v,
+                (IsfInputData::Float(val), SavedValue::Float(v), _) => *val = *v,
+                (
+                    IsfInputData::Long { value, selected },
+                    SavedValue::Long(v),
+                    isf::InputType::Long(n),
+                ) => match n.values.iter().position(|x| x == v) {
+                    Some(index) => {
+                        *value = *v;
+                        *selected = index;
+                    }
+                    None if n.values.is_empty() => *value = *v,
+                    None => (),
+                },
+                (IsfInputData::Point2d(point), SavedValue::Point2d([x, y]), _) => {
+                    *point = pt2(*x, *y);
+                }
+                (IsfInputData::Color(color), SavedValue::Color([r, g, b, a]), _) => {
+                    *color = lin_srgba(*r, *g, *b, *a);
+                }
+                _ => (),
+            }
+        }
+    }
+
     pub fn reset_events(&mut self) {
         self.inputs
             .iter_mut()
@@ -697,14 +1039,18 @@ pub fn evaluate_dimension_equation(
 
 /// Ensure the image state map is up to date.
 /// Update the GPU with new data.
+/// Imported images are looked up next to the shader first, then in the media directory.
 pub fn sync_isf_data(
     device: &wgpu::Device,
     encoder: &mut wgpu::CommandEncoder,
     isf: &isf::Isf,
     output_attachment_size: [u32; 2],
     image_loader: &ImageLoader,
+    shader_dir: &Path,
     images_path: &Path,
+    cubes: &[CubeImport],
     audio_source: &mut AudioSource,
+    image_choices: &HashMap<InputName, ImageChoice>,
     isf_data: &mut IsfData,
     num_samples: u32,
 ) -> bool {
@@ -715,17 +1061,45 @@ pub fn sync_isf_data(
         .imported
         .retain(|name, _| isf.imported.contains_key(name));
     for (key, img) in &isf.imported {
-        let path = images_path.join(img.path.clone());
         let state = isf_data
             .imported
             .entry(key.clone())
             .or_insert(ImageState::None);
 
-        if state.update(device, encoder, image_loader, path) {
-            textures_updated = true;
+        match util::imported_path(shader_dir, images_path, &img.path) {
+            Ok(path) => {
+                if state.update(device, encoder, image_loader, path) {
+                    textures_updated = true;
+                }
+            }
+            Err(msg) => {
+                if let ImageState::None = state {
+                    *state = ImageState::Ready(Err(ImageLoadError::Missing(msg)));
+                    textures_updated = true;
+                }
+            }
         }
     }
 
+    // Update imported cube maps, only reloading the ones whose faces changed.
+    let mut cube_maps = std::mem::take(&mut isf_data.cube_maps);
+    for cube in cubes {
+        let existing = cube_maps
+            .iter()
+            .position(|(name, map)| name == &cube.name && map.paths == cube.paths);
+        let cube_map = match existing {
+            Some(index) => cube_maps.remove(index).1,
+            None => {
+                textures_updated = true;
+                CubeMap::load(device, encoder, cube, shader_dir, images_path)
+            }
+        };
+        isf_data.cube_maps.push((cube.name.clone(), cube_map));
+    }
+    if !cube_maps.is_empty() {
+        textures_updated = true;
+    }
+
     // Remove old inputs - do any cleanup here
     isf_data.inputs.retain(|key, input| {
         let keep = isf.inputs.iter().map(|i| &i.name).any(|n| n == key);
@@ -740,23 +1114,14 @@ pub fn sync_isf_data(
         let input_data = isf_data
             .inputs
             .entry(input.name.clone())
-            .or_insert_with(|| {
-                IsfInputData::new(
-                    device,
-                    encoder,
-                    image_loader,
-                    images_path,
-                    audio_source,
-                    input,
-                    output_attachment_size,
-                )
-            });
+            .or_insert_with(|| IsfInputData::new(device, audio_source, image_choices, input));
         if input_data.update(
             device,
             encoder,
             image_loader,
             images_path,
             audio_source,
+            image_choices,
             input,
             output_attachment_size,
         ) {
@@ -787,28 +1152,38 @@ pub fn sync_isf_data(
             }
         }
 
-        // if a texture already exists and the size hasn't changed, return that
-        if !passes.is_empty() {
-            let pass_textures = passes.remove(0);
-            let size = pass_textures.size();
-            if size[0] == width && size[1] == height {
-                if !p.persistent {
-                    // clear the texture if it isn't persistent
-                    let data = vec![0u8; pass_textures.size_bytes()];
-                    pass_textures.upload_data(device, encoder, &data);
-                }
+        // if a texture already exists and the size and format haven't changed, return that
+        let previous = if passes.is_empty() {
+            None
+        } else {
+            Some(passes.remove(0))
+        };
+        let reusable = previous.as_ref().map_or(false, |t| {
+            t.size() == [width, height] && t.is_float() == p.float
+        });
+        if reusable {
+            let pass_textures = previous.unwrap();
+            if !p.persistent {
+                // clear the texture if it isn't persistent
+                let data = vec![0u8; pass_textures.size_bytes()];
+                pass_textures.upload_data(device, encoder, &data);
+            }
 
-                isf_data.passes.push(pass_textures);
-                continue;
+            isf_data.passes.push(pass_textures);
+            continue;
+        }
+
+        let pass_textures =
+            IsfPassTextures::new(device, encoder, [width, height], num_samples, p.float);
+
+        // persistent buffers keep their state when the output is resized
+        if let Some(previous) = &previous {
+            if p.persistent && previous.is_float() == p.float {
+                pass_textures.copy_from(encoder, previous);
             }
         }
 
-        isf_data.passes.push(IsfPassTextures::new(
-            device,
-            encoder,
-            [width, height],
-            num_samples,
-        ));
+        isf_data.passes.push(pass_textures);
     }
 
     textures_updated
@@ -847,7 +1222,7 @@ pub fn isf_data_textures<'a>(isf_data: &'a IsfData, isf: &'a isf::Isf) -> Vec<&'
 
         let texture = match input {
             IsfInputData::Image(ref img_input) => match &img_input.source {
-                ImageSource::Image(ref image_state) => match &image_state {
+                ImageSource::Image(_, ref image_state) => match &image_state {
                     ImageState::Ready(Ok(ref data)) => &data.texture,
                     ImageState::Loading(ref loading_image) => &loading_image.texture,
                     _ => continue,
@@ -855,6 +1230,10 @@ pub fn isf_data_textures<'a>(isf_data: &'a IsfData, isf: &'a isf::Isf) -> Vec<&'
                 ImageSource::Video(ref video) | ImageSource::Webcam(ref video) => {
                     &video.video_texture
                 }
+                ImageSource::Program(output) => match output.pipeline.output_texture() {
+                    Some(texture) => texture,
+                    None => continue,
+                },
                 _ => continue,
             },
             IsfInputData::Audio(audio) => &audio.audio_texture,
@@ -900,6 +1279,7 @@ fn color_as_bytes(data: &LinSrgba) -> &[u8] {
     unsafe { wgpu::bytes::from(data) }
 }
 
+/// The contents of the `IsfDataInputs` uniform buffer, each value at its std140 offset.
 pub fn get_isf_input_uniforms_bytes_vec(isf_opt: &Option<isf::Isf>, isf_data: &IsfData) -> Vec<u8> {
     let isf = match isf_opt {
         Some(i) => i,
@@ -907,20 +1287,79 @@ pub fn get_isf_input_uniforms_bytes_vec(isf_opt: &Option<isf::Isf>, isf_data: &I
     };
 
     let data_inputs = isf_data.inputs();
-    let mut bytes = vec![];
-
-    for input in &isf.inputs {
-        let data = data_inputs.get(&input.name).unwrap();
-        match data {
-            IsfInputData::Event { happening } => bytes.extend(int_as_bytes(&(*happening as i32))),
-            IsfInputData::Bool(val) => bytes.extend(int_as_bytes(&(*val as i32))),
-            IsfInputData::Float(val) => bytes.extend(float_as_bytes(val)),
-            IsfInputData::Long { value, .. } => bytes.extend(int_as_bytes(value)),
-            IsfInputData::Point2d(point) => bytes.extend(point_as_bytes(point)),
-            IsfInputData::Color(color) => bytes.extend(color_as_bytes(color)),
-            _ => (),
-        }
+    let (fields, size) = shader::isf_data_inputs_layout(&isf.inputs);
+    let mut bytes = vec![0u8; size];
+
+    for (name, offset) in fields {
+        let data = data_inputs.get(name).unwrap();
+        let value = match data {
+            IsfInputData::Event { happening } => int_as_bytes(&(*happening as i32)).to_vec(),
+            IsfInputData::Bool(val) => int_as_bytes(&(*val as i32)).to_vec(),
+            IsfInputData::Float(val) => float_as_bytes(val).to_vec(),
+            IsfInputData::Long { value, .. } => int_as_bytes(value).to_vec(),
+            IsfInputData::Point2d(point) => point_as_bytes(point).to_vec(),
+            IsfInputData::Color(color) => color_as_bytes(color).to_vec(),
+            _ => continue,
+        };
+        bytes[offset..offset + value.len()].copy_from_slice(&value);
     }
 
     bytes
 }
+
+#[cfg(test)]
+#[test]
+fn test_isf_input_uniforms_bytes() {
+    let source = r#"/*{
+        "INPUTS": [
+            { "NAME": "level", "TYPE": "float" },
+            { "NAME": "center", "TYPE": "point2D" },
+            { "NAME": "flip", "TYPE": "bool" },
+            { "NAME": "tint", "TYPE": "color" },
+            { "NAME": "image", "TYPE": "image" },
+            { "NAME": "mode", "TYPE": "long", "VALUES": [1, 2], "LABELS": ["a", "b"] }
+        ]
+    }*/
+    void main() {}
+    "#;
+    let isf = util::parse_isf(source).unwrap();
+
+    let mut isf_data = IsfData::default();
+    let inputs = isf_data.inputs_mut();
+    inputs.insert(String::from("level"), IsfInputData::Float(0.5));
+    inputs.insert(
+        String::from("center"),
+        IsfInputData::Point2d(pt2(10.0, 20.0)),
+    );
+    inputs.insert(String::from("flip"), IsfInputData::Bool(true));
+    let tint = lin_srgba(0.1, 0.2, 0.3, 0.4);
+    inputs.insert(String::from("tint"), IsfInputData::Color(tint));
+    let mode = IsfInputData::Long {
+        value: 2,
+        selected: 1,
+    };
+    inputs.insert(String::from("mode"), mode);
+
+    let bytes = get_isf_input_uniforms_bytes_vec(&Some(isf), &isf_data);
+    let float_at = |offset: usize| {
+        let mut b = [0u8; 4];
+        b.copy_from_slice(&bytes[offset..offset + 4]);
+        f32::from_ne_bytes(b)
+    };
+    let int_at = |offset: usize| {
+        let mut b = [0u8; 4];
+        b.copy_from_slice(&bytes[offset..offset + 4]);
+        i32::from_ne_bytes(b)
+    };
+
+    // float at 0, vec2 at 8, bool at 16, vec4 at 32 and int at 48, padded to 64
+    assert_eq!(bytes.len(), 64);
+    assert_eq!(float_at(0), 0.5);
+    assert_eq!((float_at(8), float_at(12)), (10.0, 20.0));
+    assert_eq!(int_at(16), 1);
+    assert_eq!(
+        (float_at(32), float_at(36), float_at(40), float_at(44)),
+        (0.1, 0.2, 0.3, 0.4)
+    );
+    assert_eq!(int_at(48), 2);
+}
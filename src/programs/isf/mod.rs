@@ -2,16 +2,22 @@
 
 use nannou::prelude::*;
 use nannou::ui::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::path::{Path, PathBuf};
 use threadpool::ThreadPool;
 
+use crate::programs::cache;
 use crate::programs::uniforms::audio_source::AudioSource;
 
+pub mod chain;
+#[cfg(test)]
+mod conformance;
 pub mod data;
-mod shader;
-mod util;
+pub mod shader;
+pub mod util;
+pub mod validation;
+pub mod values;
 
 #[repr(C)]
 #[derive(Clone, Copy)]
@@ -19,6 +25,11 @@ struct Vertex {
     position: [f32; 2],
 }
 
+/// Custom `.vs` vertex shaders share the fragment shader's ISF declarations.
+const VISIBILITY: wgpu::ShaderStage = wgpu::ShaderStage::from_bits_truncate(
+    wgpu::ShaderStage::VERTEX.bits() | wgpu::ShaderStage::FRAGMENT.bits(),
+);
+
 const VERTICES: [Vertex; 4] = [
     Vertex {
         position: [-1.0, -1.0],
@@ -37,26 +48,72 @@ const VERTICES: [Vertex; 4] = [
 /// Timing information passed into the shader.
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct IsfTime {
-    /// The time since the start of the application.
+    /// The time since the program started.
     pub time: f32,
     /// The time since the last frame was rendered.
     pub time_delta: f32,
-    /// The date as year, month, day and seconds.
+    /// The date as year, month (1-12), day and seconds since midnight.
     pub date: [f32; 4],
-    /// The current frame that is to be rendered.
+    /// The current frame that is to be rendered, starting at 0.
     pub frame_index: i32,
+    started: bool,
+}
+
+impl IsfTime {
+    /// Move on to the next frame, `time_delta` is the time since the last update.
+    /// The first frame has index 0 and starts the clock at 0.
+    pub fn advance(&mut self, time_delta: f32) {
+        if self.started {
+            self.time += time_delta;
+            self.time_delta = time_delta;
+            self.frame_index += 1;
+        } else {
+            self.started = true;
+        }
+
+        self.date = crate::util::current_date();
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_isf_time_advance() {
+    let mut isf_time = IsfTime::default();
+
+    isf_time.advance(0.5);
+    assert_eq!(isf_time.time, 0.0);
+    assert_eq!(isf_time.time_delta, 0.0);
+    assert_eq!(isf_time.frame_index, 0);
+
+    isf_time.advance(0.25);
+    isf_time.advance(0.25);
+    assert_eq!(isf_time.time, 0.5);
+    assert_eq!(isf_time.time_delta, 0.25);
+    assert_eq!(isf_time.frame_index, 2);
+    assert!(isf_time.date[1] >= 1.0 && isf_time.date[1] <= 12.0);
 }
 
 /// A render pipeline designed for hotloading!
 pub struct IsfPipeline {
+    pub defines: HashMap<String, String>,
     pub isf: Option<isf::Isf>,
     pub isf_data: data::IsfData,
     pub widget_ids: Option<HashMap<String, widget::Id>>,
     pub isf_err: Option<util::IsfError>,
     pub image_loader: data::ImageLoader,
+    /// The sources image inputs are opened with.
+    pub image_choices: HashMap<String, data::ImageChoice>,
+    pub image_options: data::ImageOptions,
+    /// The cube maps listed in the shader's `IMPORTED` section.
+    pub cube_imports: Vec<util::CubeImport>,
+    /// The `point2D` inputs that follow the mouse while it's dragged on the canvas.
+    pub mouse_points: HashSet<String>,
     pub updated: bool,
     pub pass_index: u32,
     audio_source: AudioSource,
+    defines_changed: bool,
+    imports_path: PathBuf,
+    spirv_cache: cache::SpirvCache,
     vs: shader::Shader,
     fs: shader::Shader,
     sampler: wgpu::Sampler,
@@ -70,11 +127,14 @@ pub struct IsfPipeline {
     isf_textures_bind_group: wgpu::BindGroup,
     layout: wgpu::PipelineLayout,
     render_pipeline: Option<wgpu::RenderPipeline>,
+    float_render_pipeline: Option<wgpu::RenderPipeline>,
     vertex_buffer: wgpu::Buffer,
     dst_format: wgpu::TextureFormat,
     dst_texture_size: [u32; 2],
     dst_sample_count: u32,
     texture_reshaper: Option<wgpu::TextureReshaper>,
+    /// Drawn to when the pipeline feeds another one and has no passes of its own.
+    offscreen: Option<data::IsfPassTextures>,
 }
 
 fn isf_uniforms_as_bytes(data: &data::IsfUniforms) -> &[u8] {
@@ -92,15 +152,24 @@ fn create_isf_textures_bind_group_layout(
     isf_opt: &Option<isf::Isf>,
 ) -> wgpu::BindGroupLayout {
     // Begin with the sampler.
-    let mut builder = wgpu::BindGroupLayoutBuilder::new().sampler(wgpu::ShaderStage::FRAGMENT, false);
+    let mut builder = wgpu::BindGroupLayoutBuilder::new().sampler(VISIBILITY, false);
 
     if let Some(ref isf) = isf_opt {
         for texture in data::isf_data_textures(isf_data, isf) {
             builder = builder.texture(
-                wgpu::ShaderStage::FRAGMENT,
+                VISIBILITY,
                 false,
                 wgpu::TextureViewDimension::D2,
-                texture.sample_type(),
+                crate::util::texture_sample_type(texture),
+            );
+        }
+
+        for (_, cube_map) in isf_data.cube_maps() {
+            builder = builder.texture(
+                VISIBILITY,
+                false,
+                wgpu::TextureViewDimension::Cube,
+                crate::util::texture_sample_type(&cube_map.texture),
             );
         }
     }
@@ -121,6 +190,13 @@ fn create_isf_textures_bind_group(
         Some(ref isf) => data::isf_data_textures(isf_data, isf)
             .iter()
             .map(|tex| tex.view().build())
+            .chain(isf_data.cube_maps().iter().map(|(_, cube_map)| {
+                cube_map
+                    .texture
+                    .view()
+                    .dimension(wgpu::TextureViewDimension::Cube)
+                    .build()
+            }))
             .collect(),
         None => vec![],
     };
@@ -161,9 +237,34 @@ fn create_render_pipeline(
         .build(device)
 }
 
+// Passes with `"FLOAT": true` render to a different format so they need their own pipeline.
+fn create_float_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    vs_mod: &wgpu::ShaderModule,
+    fs_mod: &wgpu::ShaderModule,
+    isf_opt: &Option<isf::Isf>,
+) -> Option<wgpu::RenderPipeline> {
+    let has_float_pass = isf_opt
+        .as_ref()
+        .map_or(false, |isf| isf.passes.iter().any(|p| p.float));
+    if !has_float_pass {
+        return None;
+    }
+
+    Some(create_render_pipeline(
+        device,
+        layout,
+        vs_mod,
+        fs_mod,
+        data::FLOAT_TEXTURE_FORMAT,
+        1,
+    ))
+}
+
 fn build_uniform_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
     wgpu::BindGroupLayoutBuilder::new()
-        .uniform_buffer(wgpu::ShaderStage::FRAGMENT, false)
+        .uniform_buffer(VISIBILITY, false)
         .build(device)
 }
 
@@ -178,16 +279,36 @@ impl IsfPipeline {
         dst_sample_count: u32,
         images_path: &Path,
         num_samples: u32,
+        imports_path: PathBuf,
+        defines: HashMap<String, String>,
+        spirv_cache: cache::SpirvCache,
+        image_choices: HashMap<String, data::ImageChoice>,
     ) -> Self {
         let isf_res = util::read_isf_from_path(&fs_path);
         let (isf, error) = util::split_result(isf_res);
+        let cube_imports = util::read_cube_imports(&fs_path);
+        let shader_dir = fs_path.parent().unwrap_or(images_path).to_path_buf();
 
         // Create the shaders
-        let fs = shader::Shader::fragment_from_path(device, fs_path);
         let vs = match vs_path {
             None => shader::Shader::vertex_default(device),
-            Some(vs_path) => shader::Shader::vertex_from_path(device, vs_path),
+            Some(ref vs_path) => shader::Shader::vertex_from_path(
+                device,
+                vs_path.clone(),
+                &fs_path,
+                &imports_path,
+                &defines,
+                &spirv_cache,
+            ),
         };
+        let fs = shader::Shader::fragment_from_path(
+            device,
+            fs_path,
+            vs_path.as_deref(),
+            &imports_path,
+            &defines,
+            &spirv_cache,
+        );
 
         dbg!(&vs);
         dbg!(&fs);
@@ -207,8 +328,11 @@ impl IsfPipeline {
                 isf,
                 dst_texture_size,
                 &image_loader,
+                &shader_dir,
                 &images_path,
+                &cube_imports,
                 &mut audio_source,
+                &image_choices,
                 &mut isf_data,
                 num_samples,
             );
@@ -293,6 +417,12 @@ impl IsfPipeline {
             )),
             _ => None,
         };
+        let float_render_pipeline = match (vs.module.as_ref(), fs.module.as_ref()) {
+            (Some(vs_mod), Some(fs_mod)) => {
+                create_float_render_pipeline(device, &layout, vs_mod, fs_mod, &isf)
+            }
+            _ => None,
+        };
 
         // The quad vertex buffer.
         let vertices_bytes = vertices_as_bytes(&VERTICES[..]);
@@ -313,14 +443,22 @@ impl IsfPipeline {
         };
 
         Self {
+            defines,
+            defines_changed: false,
+            imports_path,
             isf,
             isf_data,
             isf_err: error,
             widget_ids: None,
             updated: false,
             audio_source,
+            spirv_cache,
             pass_index: 0,
             image_loader,
+            image_choices,
+            image_options: Default::default(),
+            cube_imports,
+            mouse_points: HashSet::new(),
             vs,
             fs,
             sampler,
@@ -334,11 +472,13 @@ impl IsfPipeline {
             isf_textures_bind_group,
             layout,
             render_pipeline,
+            float_render_pipeline,
             vertex_buffer,
             dst_format,
             dst_texture_size,
             dst_sample_count,
             texture_reshaper,
+            offscreen: None,
         }
     }
 
@@ -364,36 +504,98 @@ impl IsfPipeline {
         I: IntoIterator,
         I::Item: AsRef<Path>,
     {
+        let touched_shaders = touched_shaders
+            .into_iter()
+            .map(|p| p.as_ref().to_path_buf())
+            .collect::<Vec<PathBuf>>();
+
+        // Programs feeding image inputs see the same file changes.
+        for input in self.isf_data.inputs_mut().values_mut() {
+            if let data::IsfInputData::Image(data::ImageInput {
+                source: data::ImageSource::Program(output),
+                ..
+            }) = input
+            {
+                output.pipeline.encode_update(
+                    device,
+                    encoder,
+                    images_path,
+                    touched_shaders.clone(),
+                    1,
+                );
+            }
+        }
+
         // UPDATE SHADERS
         // --------------
 
+        // Touching an imported file or changing a define recompiles the fragment shader.
+        // A custom vertex shader shares the fragment shader's ISF header and varyings so
+        // touching either one recompiles both.
+        let fs_path = self.fs.source.as_path().map(|p| p.to_path_buf());
+        let vs_path = self.vs.source.as_path().map(|p| p.to_path_buf());
+        let mut touched_shaders = touched_shaders
+            .into_iter()
+            .map(|p| match &fs_path {
+                Some(fs_path) if self.fs.imports.contains(&p) => fs_path.clone(),
+                Some(fs_path) if self.vs.imports.contains(&p) => fs_path.clone(),
+                Some(fs_path) if vs_path.as_ref() == Some(&p) => fs_path.clone(),
+                _ => p,
+            })
+            .collect::<Vec<PathBuf>>();
+        if self.defines_changed {
+            touched_shaders.extend(fs_path.clone());
+            self.defines_changed = false;
+        }
+        touched_shaders.dedup();
+
         // Attempt to recompile touched shaders.
         let mut shader_recompiled = false;
         let mut isf_updated = false;
         for path in touched_shaders {
-            let path = path.as_ref();
-            if self.vs.source.as_path() == Some(&path) {
-                let (module, error) = shader::compile_shader(device, &path);
-                self.vs.error = error;
-                if module.is_some() {
-                    shader_recompiled = true;
-                    self.vs.module = module;
+            let path = path.as_path();
+            if self.fs.source.as_path() == Some(&path) {
+                if let Some(ref vs_path) = vs_path {
+                    let (module, error) = shader::compile_isf_vertex_shader(
+                        device,
+                        vs_path,
+                        &path,
+                        &self.imports_path,
+                        &self.defines,
+                        &self.spirv_cache,
+                    );
+                    self.vs.error = error;
+                    self.vs.imports = shader::read_imports(vs_path, &self.imports_path);
+                    if module.is_some() {
+                        shader_recompiled = true;
+                        self.vs.module = module;
+                    }
                 }
-            } else if self.fs.source.as_path() == Some(&path) {
-                let (module, error) = shader::compile_isf_shader(device, &path);
+
+                let (module, error) = shader::compile_isf_shader(
+                    device,
+                    &path,
+                    vs_path.as_deref(),
+                    &self.imports_path,
+                    &self.defines,
+                    &self.spirv_cache,
+                );
                 self.fs.error = error;
+                self.fs.imports = shader::read_imports(&path, &self.imports_path);
                 if module.is_some() {
                     shader_recompiled = true;
                     self.fs.module = module;
                 }
+                // Cube maps aren't part of the `Isf` instance, they're synced with the textures.
+                self.cube_imports = util::read_cube_imports(&path);
                 // Update the `Isf` instance.
                 let isf_res = util::read_isf_from_path(&path);
                 let (new_isf, new_isf_err) = util::split_result(isf_res);
                 self.isf_err = new_isf_err;
+                // Inputs keep their values and sessions unless they're removed or change type.
                 if (self.isf.is_none() || new_isf.is_some()) && self.isf != new_isf {
                     isf_updated = true;
                     self.isf = new_isf;
-                    self.isf_data.end_session(&mut self.audio_source);
                 }
             }
         }
@@ -408,14 +610,18 @@ impl IsfPipeline {
         };
 
         // Synchronise the ISF data.
+        let shader_dir = fs_path.as_deref().and_then(Path::parent).unwrap_or(images_path);
         let textures_updated = data::sync_isf_data(
             device,
             encoder,
             isf,
             self.dst_texture_size,
             &self.image_loader,
+            shader_dir,
             images_path,
+            &self.cube_imports,
             &mut self.audio_source,
+            &self.image_choices,
             &mut self.isf_data,
             num_samples,
         );
@@ -493,6 +699,8 @@ impl IsfPipeline {
                     self.dst_format,
                     self.dst_sample_count,
                 ));
+                self.float_render_pipeline =
+                    create_float_render_pipeline(device, &self.layout, vs_mod, fs_mod, &self.isf);
             }
         }
 
@@ -520,13 +728,101 @@ impl IsfPipeline {
         dst_texture: &wgpu::TextureViewHandle,
         isf_time: IsfTime,
     ) {
-        if let Some(ref pipeline) = self.render_pipeline {
+        // Programs feeding image inputs are drawn before the first pass samples them.
+        if self.pass_index == 0 {
+            self.encode_program_sources(device, encoder, isf_time);
+        }
+
+        self.encode_pass(device, encoder, dst_texture, isf_time, self.pass_index);
+    }
+
+    fn encode_program_sources(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        isf_time: IsfTime,
+    ) {
+        for input in self.isf_data.inputs().values() {
+            if let data::IsfInputData::Image(data::ImageInput {
+                source: data::ImageSource::Program(output),
+                ..
+            }) = input
+            {
+                output.pipeline.encode_passes(device, encoder, isf_time);
+            }
+        }
+    }
+
+    /// Encode every pass in order, for pipelines that are drawn to a texture instead of the
+    /// window. Pipelines without passes are drawn to their offscreen texture.
+    pub fn encode_passes(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        isf_time: IsfTime,
+    ) {
+        self.encode_program_sources(device, encoder, isf_time);
+
+        if self.isf_data.passes().is_empty() {
+            if let Some(output) = &self.offscreen {
+                let texture_view = output.render_texture.view().build();
+                self.encode_pass(device, encoder, &texture_view, isf_time, 0);
+                crate::util::copy_texture(encoder, &output.render_texture, &output.uniform_texture);
+            }
+        }
+
+        for (index, pass) in self.isf_data.passes().iter().enumerate() {
+            let texture_view = pass.render_texture.view().build();
+            self.encode_pass(device, encoder, &texture_view, isf_time, index as u32);
+            crate::util::copy_texture(encoder, &pass.render_texture, &pass.uniform_texture);
+        }
+    }
+
+    /// Create the texture the pipeline is drawn to when it feeds another pipeline.
+    pub fn render_offscreen(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        if self.offscreen.is_none() {
+            self.offscreen = Some(data::IsfPassTextures::new(
+                device,
+                encoder,
+                self.dst_texture_size,
+                1,
+                false,
+            ));
+        }
+    }
+
+    /// The texture holding the last frame drawn by `encode_passes`.
+    pub fn output_texture(&self) -> Option<&wgpu::Texture> {
+        self.isf_data
+            .get_final_texture()
+            .or_else(|| self.offscreen.as_ref().map(|o| &o.uniform_texture))
+    }
+
+    fn encode_pass(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        dst_texture: &wgpu::TextureViewHandle,
+        isf_time: IsfTime,
+        pass_index: u32,
+    ) {
+        let pass = self.isf_data.passes().get(pass_index as usize);
+        let pipeline = match pass {
+            Some(pass) if pass.is_float() => self.float_render_pipeline.as_ref(),
+            _ => self.render_pipeline.as_ref(),
+        };
+
+        if let Some(pipeline) = pipeline {
             // Encode an update for the ISF uniform buffer.
-            let [w, h] = self.dst_texture_size;
+            // Passes with their own WIDTH and HEIGHT report their size as RENDERSIZE.
+            let [w, h] = match pass {
+                Some(pass) => pass.size(),
+                None => self.dst_texture_size,
+            };
             let isf_uniforms = data::IsfUniforms {
                 date: isf_time.date,
                 render_size: [w as f32, h as f32],
-                pass_index: self.pass_index as i32,
+                pass_index: pass_index as i32,
                 time: isf_time.time,
                 time_delta: isf_time.time_delta,
                 frame_index: isf_time.frame_index,
@@ -650,6 +946,7 @@ impl IsfPipeline {
                 }
                 isf::InputType::Point2d(_) => {
                     widget_ids.insert(name.clone() + "-label", ui.generate_widget_id());
+                    widget_ids.insert(name.clone() + "-mouse", ui.generate_widget_id());
                     widget_ids.insert(name.clone() + "-x", ui.generate_widget_id());
                     widget_ids.insert(name.clone() + "-y", ui.generate_widget_id());
                 }
@@ -667,10 +964,206 @@ impl IsfPipeline {
         self.widget_ids = Some(widget_ids);
     }
 
+    /// The fragment shader's path.
+    pub fn fs_path(&self) -> Option<&Path> {
+        self.fs.source.as_path()
+    }
+
+    /// Open an image input with a new file or device.
+    pub fn set_image_choice(&mut self, input_name: &str, choice: data::ImageChoice) {
+        self.image_choices.insert(input_name.to_string(), choice.clone());
+        if let Some(data::IsfInputData::Image(image_input)) =
+            self.isf_data.inputs_mut().get_mut(input_name)
+        {
+            image_input.select(choice);
+        }
+    }
+
+    /// Whether the ISF declares an image input with the name.
+    pub fn has_image_input(&self, input_name: &str) -> bool {
+        matches!(
+            self.isf_data.inputs().get(input_name),
+            Some(data::IsfInputData::Image(_))
+        )
+    }
+
+    /// Feed an image input with the output of another ISF program.
+    /// The pipeline is handed back if there is no such image input.
+    pub fn set_program_source(
+        &mut self,
+        input_name: &str,
+        program_name: &str,
+        pipeline: IsfPipeline,
+    ) -> Option<IsfPipeline> {
+        match self.isf_data.inputs_mut().get_mut(input_name) {
+            Some(data::IsfInputData::Image(image_input)) => {
+                image_input.set_program(program_name, pipeline);
+                self.image_choices.insert(
+                    input_name.to_string(),
+                    data::ImageChoice::Program(program_name.to_string()),
+                );
+                self.updated = true;
+                None
+            }
+            _ => Some(pipeline),
+        }
+    }
+
+    /// Stop feeding an image input with another program, handing the program back.
+    pub fn take_program_source(&mut self, input_name: &str) -> Option<IsfPipeline> {
+        match self.isf_data.inputs_mut().get_mut(input_name) {
+            Some(data::IsfInputData::Image(image_input)) => {
+                let pipeline = image_input.take_program()?;
+                self.image_choices.remove(input_name);
+                self.updated = true;
+                Some(pipeline)
+            }
+            _ => None,
+        }
+    }
+
+    /// The program feeding an image input.
+    pub fn program_source_mut(&mut self, input_name: &str) -> Option<&mut IsfPipeline> {
+        match self.isf_data.inputs_mut().get_mut(input_name) {
+            Some(data::IsfInputData::Image(data::ImageInput {
+                source: data::ImageSource::Program(output),
+                ..
+            })) => Some(&mut output.pipeline),
+            _ => None,
+        }
+    }
+
+    /// Change the sample count of the texture the pipeline is drawn to.
+    /// The render pipeline is recreated on the next update.
+    pub fn set_dst_sample_count(&mut self, sample_count: u32) {
+        if self.dst_sample_count != sample_count {
+            self.dst_sample_count = sample_count;
+            self.updated = true;
+        }
+    }
+
+    /// Record the input values of this pipeline and the programs feeding it.
+    pub fn save_values(&self, saved: &mut values::SavedValues) {
+        if let Some(path) = self.fs_path() {
+            let inputs = self.isf_data.saved_values();
+            if !inputs.is_empty() {
+                saved.insert(values::shader_key(path), inputs);
+            }
+        }
+
+        for input in self.isf_data.inputs().values() {
+            if let data::IsfInputData::Image(data::ImageInput {
+                source: data::ImageSource::Program(output),
+                ..
+            }) = input
+            {
+                output.pipeline.save_values(saved);
+            }
+        }
+    }
+
+    /// Set the inputs to the values saved for the shader.
+    pub fn restore_values(&mut self, saved: &values::SavedValues) {
+        let inputs = match self.fs_path().and_then(|p| saved.get(&values::shader_key(p))) {
+            Some(inputs) => inputs,
+            None => return,
+        };
+
+        if let Some(ref isf) = self.isf {
+            self.isf_data.restore_values(isf, inputs);
+        }
+    }
+
+    /// The `point2D` inputs bound to the mouse and their values, in the order they're declared.
+    pub fn mouse_point_values(&self) -> Vec<(String, Point2)> {
+        let isf = match &self.isf {
+            Some(isf) => isf,
+            None => return vec![],
+        };
+
+        isf.inputs
+            .iter()
+            .filter(|input| self.mouse_points.contains(&input.name))
+            .filter_map(|input| match self.isf_data.inputs().get(&input.name) {
+                Some(data::IsfInputData::Point2d(point)) => Some((input.name.clone(), *point)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Set a `point2D` input, keeping it within the input's `MIN` and `MAX`.
+    pub fn set_point(&mut self, input_name: &str, mut point: Point2) {
+        let input = self
+            .isf
+            .as_ref()
+            .and_then(|isf| isf.inputs.iter().find(|i| i.name == input_name));
+        let (min, max) = match input.map(|i| &i.ty) {
+            Some(isf::InputType::Point2d(input_config)) => (input_config.min, input_config.max),
+            _ => return,
+        };
+
+        if let Some([x, y]) = min {
+            point.x = point.x.max(x);
+            point.y = point.y.max(y);
+        }
+        if let Some([x, y]) = max {
+            point.x = point.x.min(x);
+            point.y = point.y.min(y);
+        }
+
+        if let Some(data::IsfInputData::Point2d(p)) =
+            self.isf_data.inputs_mut().get_mut(input_name)
+        {
+            *p = point;
+        }
+    }
+
+    /// Change the value of a preprocessor define.
+    /// The fragment shader will be recompiled on the next update.
+    pub fn set_define(&mut self, name: &str, value: String) {
+        self.defines.insert(name.to_string(), value);
+        self.defines_changed = true;
+    }
+
     pub fn end_session(&mut self) {
         self.isf_data.end_session(&mut self.audio_source);
     }
 
+    /// Restart the input sessions of a pipeline that was previously ended.
+    pub fn start_session(&mut self) {
+        self.isf_data.start_session(&mut self.audio_source);
+        self.pass_index = 0;
+        self.updated = true;
+    }
+
+    /// The files this pipeline is built from, including imports and the programs feeding it.
+    pub fn dependencies(&self) -> Vec<PathBuf> {
+        let sources = self.isf_data.inputs().values().filter_map(|input| match input {
+            data::IsfInputData::Image(data::ImageInput {
+                source: data::ImageSource::Program(output),
+                ..
+            }) => Some(output.pipeline.dependencies()),
+            _ => None,
+        });
+
+        [&self.vs, &self.fs]
+            .iter()
+            .flat_map(|shader| {
+                shader
+                    .source
+                    .as_path()
+                    .map(|path| path.to_path_buf())
+                    .into_iter()
+                    .chain(shader.imports.iter().cloned())
+            })
+            .chain(sources.flatten())
+            .collect()
+    }
+
+    pub fn dst_texture_size(&self) -> [u32; 2] {
+        self.dst_texture_size
+    }
+
     pub fn pause(&mut self) {
         self.isf_data.pause(&mut self.audio_source);
     }
@@ -2,10 +2,13 @@
 
 use nannou::prelude::*;
 use regex::Regex;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+use crate::programs::cache;
 use crate::programs::isf::util;
+use crate::programs::shaders;
 
 /// Errors that might occur while loading a shader.
 #[derive(Debug, Error)]
@@ -39,16 +42,167 @@ fn inputs_require_isf_data_input(inputs: &[isf::Input]) -> bool {
     false
 }
 
+/// The GLSL type and std140 size of an input's field in `IsfDataInputs`, `None` for inputs that
+/// are bound as textures. Each of these types is aligned to its size.
+fn isf_data_input_field(ty: &isf::InputType) -> Option<(&'static str, usize)> {
+    match ty {
+        isf::InputType::Event | isf::InputType::Bool(_) => Some(("bool", 4)),
+        isf::InputType::Long(_) => Some(("int", 4)),
+        isf::InputType::Float(_) => Some(("float", 4)),
+        isf::InputType::Point2d(_) => Some(("vec2", 8)),
+        isf::InputType::Color(_) => Some(("vec4", 16)),
+        isf::InputType::Image | isf::InputType::Audio(_) | isf::InputType::AudioFft(_) => None,
+    }
+}
+
+/// The byte offset of each `IsfDataInputs` field by input name in declaration order, along with
+/// the size of the block, following the std140 layout it's declared with.
+///
+/// Fields start at the next multiple of their alignment and the block is padded to a multiple of
+/// 16 bytes.
+pub fn isf_data_inputs_layout(inputs: &[isf::Input]) -> (Vec<(&str, usize)>, usize) {
+    let round_up = |offset: usize, align: usize| (offset + align - 1) / align * align;
+
+    let mut fields = vec![];
+    let mut end = 0;
+    for input in inputs {
+        if let Some((_, size)) = isf_data_input_field(&input.ty) {
+            let offset = round_up(end, size);
+            fields.push((input.name.as_str(), offset));
+            end = offset + size;
+        }
+    }
+
+    (fields, round_up(end, 16))
+}
+
+#[cfg(test)]
+fn isf_with_inputs(inputs: &[&str]) -> isf::Isf {
+    let source = format!(
+        "/*{{\n\"INPUTS\": [{}]\n}}*/\nvoid main() {{}}\n",
+        inputs.join(",")
+    );
+    util::parse_isf(&source).unwrap()
+}
+
+#[cfg(test)]
+#[test]
+fn test_isf_data_inputs_layout() {
+    let layout = |inputs: &[&str]| {
+        let isf = isf_with_inputs(inputs);
+        let (fields, size) = isf_data_inputs_layout(&isf.inputs);
+        let offsets = fields.into_iter().map(|(_, o)| o).collect::<Vec<usize>>();
+        (offsets, size)
+    };
+    let float = r#"{ "NAME": "f", "TYPE": "float" }"#;
+    let point = r#"{ "NAME": "p", "TYPE": "point2D" }"#;
+    let color = r#"{ "NAME": "c", "TYPE": "color" }"#;
+    let image = r#"{ "NAME": "i", "TYPE": "image" }"#;
+
+    assert_eq!(layout(&[]), (vec![], 0));
+    assert_eq!(layout(&[image]), (vec![], 0));
+    assert_eq!(layout(&[float]), (vec![0], 16));
+    assert_eq!(layout(&[float, point]), (vec![0, 8], 16));
+    assert_eq!(layout(&[float, color]), (vec![0, 16], 32));
+    assert_eq!(
+        layout(&[float, image, float, float, point]),
+        (vec![0, 4, 8, 16], 32)
+    );
+    assert_eq!(layout(&[point, float, color]), (vec![0, 8, 16], 32));
+    assert_eq!(layout(&[color, float]), (vec![0, 16], 32));
+}
+
+#[cfg(test)]
+#[test]
+fn test_isf_data_inputs_layout_orderings() {
+    fn permutations(items: Vec<&str>) -> Vec<Vec<&str>> {
+        if items.len() <= 1 {
+            return vec![items];
+        }
+        let mut all = vec![];
+        for i in 0..items.len() {
+            let mut rest = items.clone();
+            let first = rest.remove(i);
+            for mut permutation in permutations(rest) {
+                permutation.insert(0, first);
+                all.push(permutation);
+            }
+        }
+        all
+    }
+
+    let inputs = vec![
+        r#"{ "NAME": "b", "TYPE": "bool" }"#,
+        r#"{ "NAME": "e", "TYPE": "event" }"#,
+        r#"{ "NAME": "l", "TYPE": "long", "VALUES": [0, 1], "LABELS": ["a", "b"] }"#,
+        r#"{ "NAME": "f", "TYPE": "float" }"#,
+        r#"{ "NAME": "p", "TYPE": "point2D" }"#,
+        r#"{ "NAME": "c", "TYPE": "color" }"#,
+    ];
+
+    for ordering in permutations(inputs) {
+        let isf = isf_with_inputs(&ordering);
+        let (fields, size) = isf_data_inputs_layout(&isf.inputs);
+        assert_eq!(fields.len(), ordering.len());
+
+        // each field is aligned and starts at the first aligned offset after the previous one
+        let mut end = 0;
+        for (input, (name, offset)) in isf.inputs.iter().zip(fields.iter()) {
+            let (_, field_size) = isf_data_input_field(&input.ty).unwrap();
+            assert_eq!(input.name, *name);
+            assert_eq!(offset % field_size, 0, "{} in {:?}", name, ordering);
+            assert!(
+                *offset >= end && *offset - end < field_size,
+                "{:?}",
+                ordering
+            );
+            end = offset + field_size;
+        }
+
+        assert_eq!(size % 16, 0);
+        assert!(size >= end && size - end < 16, "{:?}", ordering);
+    }
+}
+
 /// Generate the necessary GLSL declarations from the given ISF to be prefixed to the GLSL string
 /// from which the ISF was parsed.
 ///
 /// This string should be inserted directly after the version preprocessor.
-pub fn glsl_string_from_isf(isf: &isf::Isf) -> String {
+pub fn glsl_string_from_isf(isf: &isf::Isf, cubes: &[util::CubeImport]) -> String {
     // The normalised coords passed through from the vertex shader.
     let frag_norm_coord_str = "
         layout(location = 0) in vec2 isf_FragNormCoord;
     ";
 
+    let mut s = frag_norm_coord_str.to_string();
+    s.push_str(&isf_declarations(isf, cubes));
+    s
+}
+
+/// Generate the declarations an ISF vertex shader is prefixed with.
+/// Along with the fragment shader's uniforms it gets the quad's vertex position and
+/// `isf_vertShaderInit`, which sets up `gl_Position` and `isf_FragNormCoord`.
+pub fn vertex_glsl_string_from_isf(isf: &isf::Isf, cubes: &[util::CubeImport]) -> String {
+    let vertex_io_str = "
+        layout(location = 0) in vec2 isf_position;
+        layout(location = 0) out vec2 isf_FragNormCoord;
+    ";
+
+    let vert_shader_init_str = "
+        void isf_vertShaderInit() {
+            gl_Position = vec4(isf_position, 0.0, 1.0);
+            isf_FragNormCoord = isf_position * 0.5 + 0.5;
+        }
+    ";
+
+    let mut s = vertex_io_str.to_string();
+    s.push_str(&isf_declarations(isf, cubes));
+    s.push_str(vert_shader_init_str);
+    s
+}
+
+/// The uniforms, textures and image functions shared by the vertex and fragment shaders.
+fn isf_declarations(isf: &isf::Isf, cubes: &[util::CubeImport]) -> String {
     // Create the `IsfData` uniform buffer with time, date, etc.
     let isf_data_str = "
         layout(set = 0, binding = 0) uniform IsfData {
@@ -92,10 +246,14 @@ pub fn glsl_string_from_isf(isf: &isf::Isf) -> String {
     }
 
     // Now create textures for the `PASSES`.
+    // Passes without a target are still bound so they count towards the bindings that follow.
     let mut pass_textures = vec![];
     for pass in &isf.passes {
         let target = match pass.target {
-            None => continue,
+            None => {
+                binding += 1;
+                continue;
+            }
             Some(ref t) => t,
         };
         let s = format!(
@@ -106,23 +264,35 @@ pub fn glsl_string_from_isf(isf: &isf::Isf) -> String {
         binding += 1;
     }
 
+    // Cube maps come last so they don't move the 2D textures' bindings.
+    let mut cube_textures = vec![];
+    for cube in cubes {
+        let s = format!(
+            "layout(set = 1, binding = {}) uniform textureCube {};\n",
+            binding, cube.name
+        );
+        cube_textures.push(s);
+        binding += 1;
+    }
+    if !cubes.is_empty() {
+        // ISF shaders sample cube maps with the GLSL 1.x function.
+        cube_textures.push(
+            "#define textureCube(img, dir) texture(samplerCube(img, img_sampler), dir)\n"
+                .to_string(),
+        );
+    }
+
     // Create the `IsfDataInputs` uniform buffer with a field for each event, float, long, bool,
     // point2d and color.
     let isf_data_input_str = match inputs_require_isf_data_input(&isf.inputs) {
         false => None,
         true => {
             let mut isf_data_input_string =
-                "\nlayout(set = 2, binding = 0) uniform IsfDataInputs {\n".to_string();
+                "\nlayout(std140, set = 2, binding = 0) uniform IsfDataInputs {\n".to_string();
             for input in &isf.inputs {
-                let ty_str = match input.ty {
-                    isf::InputType::Event | isf::InputType::Bool(_) => "bool",
-                    isf::InputType::Long(_) => "int",
-                    isf::InputType::Float(_) => "float",
-                    isf::InputType::Point2d(_) => "vec2",
-                    isf::InputType::Color(_) => "vec4",
-                    isf::InputType::Image
-                    | isf::InputType::Audio(_)
-                    | isf::InputType::AudioFft(_) => continue,
+                let ty_str = match isf_data_input_field(&input.ty) {
+                    Some((ty_str, _)) => ty_str,
+                    None => continue,
                 };
                 isf_data_input_string.push_str(&format!("{} {};\n", ty_str, input.name));
             }
@@ -165,12 +335,12 @@ pub fn glsl_string_from_isf(isf: &isf::Isf) -> String {
 
     // Combine all the declarations together.
     let mut s = String::new();
-    s.push_str(&frag_norm_coord_str);
     s.push_str(&isf_data_str);
     s.push_str(&img_sampler_str);
     s.extend(imported_textures);
     s.extend(input_textures);
     s.extend(pass_textures);
+    s.extend(cube_textures);
     s.extend(isf_data_input_str);
     s.push_str(&img_fns_str);
     s
@@ -189,12 +359,85 @@ pub fn glfragcolor_exists_and_no_out(glsl_str: &str) -> bool {
 /// out decl for it here.
 pub const FRAGCOLOR_OUT_DECL_STR: &str = "layout(location = 0) out vec4 FragColor;";
 
+fn varying_regex() -> Regex {
+    Regex::new(r"(?m)^([ \t]*)varying\s+((?:(?:lowp|mediump|highp)\s+)?\w+)\s+([^;]+);").unwrap()
+}
+
+/// The varyings an ISF vertex shader passes on, in the order they're declared.
+/// Each one's location is its index after `isf_FragNormCoord`.
+pub fn varying_names(source: &str) -> Vec<String> {
+    varying_regex()
+        .captures_iter(source)
+        .flat_map(|c| {
+            c[3].split(',')
+                .map(|name| name.split('[').next().unwrap().trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect::<Vec<String>>()
+        })
+        .collect()
+}
+
+/// Rewrite GLSL 1.x `varying` declarations as `in` or `out` with the locations given by
+/// the vertex shader's `names`. Each declaration stays on its line.
+pub fn declare_varyings(source: &str, qualifier: &str, names: &[String]) -> String {
+    let mut unknown = names.len();
+    varying_regex()
+        .replace_all(source, |c: &regex::Captures| {
+            let declarations = c[3]
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(|name| {
+                    let base = name.split('[').next().unwrap().trim();
+                    let index = match names.iter().position(|n| n == base) {
+                        Some(i) => i,
+                        None => {
+                            unknown += 1;
+                            unknown - 1
+                        }
+                    };
+                    format!(
+                        "layout(location = {}) {} {} {};",
+                        index + 1,
+                        qualifier,
+                        &c[2],
+                        name
+                    )
+                })
+                .collect::<Vec<String>>();
+            format!("{}{}", &c[1], declarations.join(" "))
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+#[test]
+fn test_declare_varyings() {
+    let vs = "varying vec2 left;\n  varying highp vec2 right, up;\nvoid main() {}\n";
+    let names = varying_names(vs);
+    assert_eq!(names, vec!["left", "right", "up"]);
+
+    assert_eq!(
+        declare_varyings(vs, "out", &names),
+        "layout(location = 1) out vec2 left;\n  layout(location = 2) out highp vec2 right; \
+         layout(location = 3) out highp vec2 up;\nvoid main() {}\n"
+    );
+
+    // the fragment shader may declare fewer varyings in any order
+    assert_eq!(
+        declare_varyings("varying vec2 up;\nvarying vec2 left;\n", "in", &names),
+        "layout(location = 3) in vec2 up;\nlayout(location = 1) in vec2 left;\n"
+    );
+}
+
 /// Inserts the ISF into the beginning of the shader, returning the resulting glsl source.
 pub fn prefix_isf_glsl_str(isf_glsl_str: &str, mut shader_string: String) -> (String, usize) {
     // Check to see if we need to declare the `gl_FragCoord` output.
-    // While we're at it, replace `vv_FragNormCoord` with `isf_FragNormCoord` if necessary.
+    // While we're at it, replace the ISF v1 `vv_` names with their `isf_` equivalents.
     let glfragcolor_decl_str = {
-        shader_string = shader_string.replace("vv_FragNormCoord", "isf_FragNormCoord");
+        shader_string = shader_string
+            .replace("vv_FragNormCoord", "isf_FragNormCoord")
+            .replace("vv_vertShaderInit", "isf_vertShaderInit");
         if glfragcolor_exists_and_no_out(&shader_string) {
             shader_string = shader_string.replace("gl_FragColor", "FragColor");
             Some(FRAGCOLOR_OUT_DECL_STR.to_string())
@@ -252,88 +495,227 @@ pub fn prefix_isf_glsl_str(isf_glsl_str: &str, mut shader_string: String) -> (St
     (output, offset)
 }
 
+/// The files imported by an ISF shader.
+pub fn read_imports(path: &Path, imports_path: &Path) -> Vec<PathBuf> {
+    match std::fs::read_to_string(path) {
+        Ok(source) => shaders::imports(imports_path, &source),
+        Err(_) => vec![],
+    }
+}
+
+/// The vertex shader for an ISF fragment shader in `folder_path`.
+///
+/// This is `vert` from the config if there is one, otherwise a `.vs` file with the same name
+/// next to the fragment shader. `None` uses the default vertex shader.
+pub fn vertex_path(folder_path: &Path, frag: &str, vert: Option<&str>) -> Option<PathBuf> {
+    match vert {
+        Some(vert) => Some(folder_path.join(vert)),
+        None => Some(folder_path.join(frag).with_extension("vs")).filter(|p| p.is_file()),
+    }
+}
+
+/// The varyings declared by a custom ISF vertex shader, including its imports.
+fn read_varyings(vs_path: &Path, imports_path: &Path) -> Vec<String> {
+    let filename = vs_path.to_string_lossy();
+    std::fs::read_to_string(vs_path)
+        .ok()
+        .and_then(|s| shaders::expand_imports(imports_path, &s, &filename).ok())
+        .map(|s| varying_names(&s))
+        .unwrap_or_default()
+}
+
+/// Reformat a compile error with the actual filename and the source line numbers.
+fn compile_error(
+    error: hotglsl::CompileError,
+    path: &Path,
+    source: &str,
+    offset: usize,
+) -> ShaderError {
+    let mut msg = error.to_string();
+    msg = msg.replacen("\n", "\n\n", 1);
+
+    // replace temp filename in message with actual file name
+    let filename_re = Regex::new(r"/[^\s]+\.(frag|vert)").unwrap();
+    let path_string = path.to_str().unwrap().to_string();
+    let filename = path_string.split("/").last().unwrap();
+    msg = filename_re.replace_all(msg.as_str(), filename).to_string();
+
+    // reformat error message with source line number and code line
+    let line_re = Regex::new(r"ERROR: ([^\.\n]+)\.(fs|vs):(\d+):([^\n]+)\n").unwrap();
+    let lines = source.lines().collect::<Vec<&str>>();
+    msg = line_re
+        .replace_all(msg.as_str(), |captures: &regex::Captures| {
+            let input = captures[0].to_string();
+            let filename = captures[1].to_string();
+            let extension = captures[2].to_string();
+            let message = captures[4].to_string();
+            let line_number = match captures[3].parse::<i32>() {
+                Ok(n) => n,
+                Err(_) => return input,
+            };
+
+            let source_line_number = line_number - offset as i32;
+            let line = match lines.get(line_number as usize - 1) {
+                Some(l) => l,
+                None => return input,
+            };
+
+            format!(
+                "{}.{}:{}:{}\n\n    >> {}\n\n",
+                filename, extension, source_line_number, message, line
+            )
+        })
+        .to_string();
+
+    println!("{}", msg);
+
+    ShaderError::Compile {
+        err: hotglsl::CompileError::GlslToSpirv { err: msg },
+    }
+}
+
+/// Prefix the ISF declarations to a shader and compile it to SPIR-V.
+///
+/// `varyings` is the qualifier and the vertex shader's varying names used to rewrite
+/// `varying` declarations when there's a custom vertex shader.
+fn compile_isf_stage_spirv(
+    path: &Path,
+    source: &str,
+    isf_str: &str,
+    varyings: Option<(&str, &[String])>,
+    ty: hotglsl::ShaderType,
+    imports_path: &Path,
+    defines: &HashMap<String, String>,
+    spirv_cache: &cache::SpirvCache,
+) -> Result<Vec<u8>, ShaderError> {
+    let filename = path.to_string_lossy();
+    let mut old_str = shaders::expand_imports(imports_path, source, &filename).map_err(|err| {
+        ShaderError::Compile {
+            err: hotglsl::CompileError::GlslToSpirv { err },
+        }
+    })?;
+    if let Some((qualifier, names)) = varyings {
+        old_str = declare_varyings(&old_str, qualifier, names);
+    }
+
+    let (prefixed_str, offset) = prefix_isf_glsl_str(isf_str, old_str);
+    let (new_str, define_lines) = shaders::inject_defines(&prefixed_str, defines);
+    let offset = offset + define_lines;
+
+    let kind = match ty {
+        hotglsl::ShaderType::Vertex => "isf_vs",
+        _ => "isf",
+    };
+    if let Some(bytes) = cache::get_spirv(spirv_cache, kind, &new_str) {
+        return Ok(bytes);
+    }
+
+    let res = hotglsl::compile_str(&new_str, ty)
+        .map_err(|error| compile_error(error, path, &new_str, offset));
+
+    if let Ok(ref bytes) = res {
+        cache::insert_spirv(spirv_cache, kind, &new_str, bytes);
+    }
+
+    res
+}
+
+/// Compile an ISF fragment shader to SPIR-V.
+///
+/// `//@import` directives are resolved relative to `imports_path`.
+/// When there's a custom vertex shader at `vs_path` the fragment shader's `varying`s are
+/// matched to its outputs.
+/// The result is looked up in and added to the SPIR-V cache.
+pub fn compile_isf_spirv(
+    path: &Path,
+    vs_path: Option<&Path>,
+    imports_path: &Path,
+    defines: &HashMap<String, String>,
+    spirv_cache: &cache::SpirvCache,
+) -> Result<Vec<u8>, ShaderError> {
+    std::fs::read_to_string(&path)
+        .map_err(ShaderError::from)
+        .and_then(|s| util::parse_isf(&s).map(|isf| (s, isf)).map_err(From::from))
+        .and_then(|(source, isf)| {
+            let cubes = util::split_cube_imports(&source).1;
+            let isf_str = glsl_string_from_isf(&isf, &cubes);
+
+            let names = vs_path.map(|p| read_varyings(p, imports_path));
+            compile_isf_stage_spirv(
+                path,
+                &source,
+                &isf_str,
+                names.as_ref().map(|n| ("in", &n[..])),
+                hotglsl::ShaderType::Fragment,
+                imports_path,
+                defines,
+                spirv_cache,
+            )
+        })
+}
+
+/// Compile a custom ISF vertex shader to SPIR-V.
+///
+/// The declarations come from the ISF header of the fragment shader at `fs_path`.
+pub fn compile_isf_vertex_spirv(
+    path: &Path,
+    fs_path: &Path,
+    imports_path: &Path,
+    defines: &HashMap<String, String>,
+    spirv_cache: &cache::SpirvCache,
+) -> Result<Vec<u8>, ShaderError> {
+    let fs_source = std::fs::read_to_string(fs_path)?;
+    let isf = util::parse_isf(&fs_source)?;
+    let cubes = util::split_cube_imports(&fs_source).1;
+    let source = std::fs::read_to_string(&path)?;
+    let isf_str = vertex_glsl_string_from_isf(&isf, &cubes);
+    let names = read_varyings(path, imports_path);
+    compile_isf_stage_spirv(
+        path,
+        &source,
+        &isf_str,
+        Some(("out", &names)),
+        hotglsl::ShaderType::Vertex,
+        imports_path,
+        defines,
+        spirv_cache,
+    )
+}
+
 /// Compile an ISF fragment shader.
 ///
 /// This is used for compiling the ISF fragment shader.
 pub fn compile_isf_shader(
     device: &wgpu::Device,
     path: &Path,
+    vs_path: Option<&Path>,
+    imports_path: &Path,
+    defines: &HashMap<String, String>,
+    spirv_cache: &cache::SpirvCache,
 ) -> (Option<wgpu::ShaderModule>, Option<ShaderError>) {
-    let res = std::fs::read_to_string(&path)
-        .map_err(ShaderError::from)
-        .and_then(|s| isf::parse(&s).map(|isf| (s, isf)).map_err(From::from))
-        .and_then(|(old_str, isf)| {
-            let isf_str = glsl_string_from_isf(&isf);
-            println!("{}", isf_str);
-
-            let (new_str, offset) = prefix_isf_glsl_str(&isf_str, old_str);
-            let ty = hotglsl::ShaderType::Fragment;
-
-            hotglsl::compile_str(&new_str, ty).map_err(|error| {
-                let mut msg = error.to_string();
-                msg = msg.replacen("\n", "\n\n", 1);
-
-                // replace temp filename in message with actual file name
-                let filename_re = Regex::new(r"/[^\s]+\.frag").unwrap();
-                let path_string = path.to_str().unwrap().to_string();
-                let filename = path_string.split("/").last().unwrap();
-                msg = filename_re.replace_all(msg.as_str(), filename).to_string();
-
-                // reformat error message with source line number and code line
-                let line_re = Regex::new(r"ERROR: ([^\.\n]+)\.fs:(\d+):([^\n]+)\n").unwrap();
-                let lines = new_str.lines().collect::<Vec<&str>>();
-                msg = line_re
-                    .replace_all(msg.as_str(), |captures: &regex::Captures| {
-                        let input = captures[0].to_string();
-                        let filename = captures[1].to_string();
-                        let message = captures[3].to_string();
-                        let line_number = match captures[2].parse::<i32>() {
-                            Ok(n) => n,
-                            Err(_) => return input,
-                        };
-
-                        let source_line_number = line_number - offset as i32;
-                        let line = match lines.get(line_number as usize - 1) {
-                            Some(l) => l,
-                            None => return input,
-                        };
-
-                        println!("line number: {}", line_number);
-                        println!("source line number: {}", source_line_number);
-                        println!("line: {}", line);
-
-                        format!(
-                            "{}.fs:{}:{}\n\n    >> {}\n\n",
-                            filename, source_line_number, message, line
-                        )
-                    })
-                    .to_string();
-
-                println!("{}", msg);
-
-                ShaderError::Compile {
-                    err: hotglsl::CompileError::GlslToSpirv { err: msg },
-                }
-            })
-        });
-
+    let res = compile_isf_spirv(path, vs_path, imports_path, defines, spirv_cache);
     let (bytes, error) = util::split_result(res);
     let module = bytes.map(|b| wgpu::shader_from_spirv_bytes(device, &b));
 
     (module, error)
 }
 
-/// Compile a regular, non-ISF shader.
+/// Compile a custom ISF vertex shader.
 ///
-/// This is used for compiling the vertex shaders.
-pub fn compile_shader(
+/// This is used for compiling the `.vs` next to an ISF fragment shader.
+pub fn compile_isf_vertex_shader(
     device: &wgpu::Device,
     path: &Path,
+    fs_path: &Path,
+    imports_path: &Path,
+    defines: &HashMap<String, String>,
+    spirv_cache: &cache::SpirvCache,
 ) -> (Option<wgpu::ShaderModule>, Option<ShaderError>) {
-    let res = hotglsl::compile(&path).map_err(ShaderError::from);
-    let (bytes, compile_err) = util::split_result(res);
+    let res = compile_isf_vertex_spirv(path, fs_path, imports_path, defines, spirv_cache);
+    let (bytes, error) = util::split_result(res);
     let module = bytes.map(|b| wgpu::shader_from_spirv_bytes(device, &b));
-    (module, compile_err)
+
+    (module, error)
 }
 
 /// Compile a regular, non-ISF shader.
@@ -368,26 +750,47 @@ impl ShaderSource {
 #[derive(Debug)]
 pub struct Shader {
     pub source: ShaderSource,
+    pub imports: Vec<PathBuf>,
     pub module: Option<wgpu::ShaderModule>,
     pub error: Option<ShaderError>,
 }
 
 impl Shader {
-    pub fn fragment_from_path(device: &wgpu::Device, path: PathBuf) -> Self {
-        let (module, error) = compile_isf_shader(device, &path);
+    pub fn fragment_from_path(
+        device: &wgpu::Device,
+        path: PathBuf,
+        vs_path: Option<&Path>,
+        imports_path: &Path,
+        defines: &HashMap<String, String>,
+        spirv_cache: &cache::SpirvCache,
+    ) -> Self {
+        let (module, error) =
+            compile_isf_shader(device, &path, vs_path, imports_path, defines, spirv_cache);
+        let imports = read_imports(&path, imports_path);
         let source = ShaderSource::Path(path);
         Shader {
             source,
+            imports,
             module,
             error,
         }
     }
 
-    pub fn vertex_from_path(device: &wgpu::Device, path: PathBuf) -> Self {
-        let (module, error) = compile_shader(device, &path);
+    pub fn vertex_from_path(
+        device: &wgpu::Device,
+        path: PathBuf,
+        fs_path: &Path,
+        imports_path: &Path,
+        defines: &HashMap<String, String>,
+        spirv_cache: &cache::SpirvCache,
+    ) -> Self {
+        let (module, error) =
+            compile_isf_vertex_shader(device, &path, fs_path, imports_path, defines, spirv_cache);
+        let imports = read_imports(&path, imports_path);
         let source = ShaderSource::Path(path);
         Shader {
             source,
+            imports,
             module,
             error,
         }
@@ -400,6 +803,7 @@ impl Shader {
         let source = ShaderSource::HardCoded;
         Shader {
             source,
+            imports: vec![],
             module,
             error,
         }
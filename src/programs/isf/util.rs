@@ -1,6 +1,6 @@
 // a fork of https://github.com/nannou-org/nannou/blob/master/nannou_isf/src/pipeline.rs
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 /// Errors that can occur while trying to load and parse ISF from the fragment shader.
@@ -21,7 +21,151 @@ pub enum IsfError {
 pub fn read_isf_from_path(path: &Path) -> Result<isf::Isf, IsfError> {
     std::fs::read_to_string(path)
         .map_err(IsfError::from)
-        .and_then(|s| isf::parse(&s).map_err(From::from))
+        .and_then(|s| parse_isf(&s).map_err(From::from))
+}
+
+/// An `IMPORTED` cube map. `isf::parse` only knows about 2D images.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubeImport {
+    pub name: String,
+    /// The faces in +X, -X, +Y, -Y, +Z, -Z order.
+    pub paths: Vec<PathBuf>,
+}
+
+/// The byte range of the JSON header inside the leading comment.
+fn header_range(source: &str) -> Option<std::ops::Range<usize>> {
+    let start = source.find("/*")? + 2;
+    let end = start + source[start..].find("*/")?;
+    Some(start..end)
+}
+
+fn is_cube(entry: &serde_json::Value) -> bool {
+    entry.get("TYPE").and_then(|t| t.as_str()) == Some("cube")
+}
+
+fn cube_import(name: &str, entry: &serde_json::Value) -> CubeImport {
+    let paths = match entry.get("PATH") {
+        Some(serde_json::Value::Array(paths)) => paths
+            .iter()
+            .filter_map(|p| p.as_str())
+            .map(PathBuf::from)
+            .collect(),
+        Some(serde_json::Value::String(path)) => vec![PathBuf::from(path)],
+        _ => vec![],
+    };
+    CubeImport {
+        name: name.to_string(),
+        paths,
+    }
+}
+
+/// Take the `"TYPE": "cube"` entries out of the header's `IMPORTED` so the rest of it can be
+/// parsed by `isf::parse`. The header keeps its line count so error line numbers still match.
+pub fn split_cube_imports(source: &str) -> (String, Vec<CubeImport>) {
+    let unchanged = (source.to_string(), vec![]);
+    let range = match header_range(source) {
+        Some(r) => r,
+        None => return unchanged,
+    };
+    let mut header: serde_json::Value = match serde_json::from_str(&source[range.clone()]) {
+        Ok(h) => h,
+        Err(_) => return unchanged,
+    };
+
+    let mut cubes = vec![];
+    match header.get_mut("IMPORTED") {
+        Some(serde_json::Value::Object(imported)) => {
+            let names = imported
+                .iter()
+                .filter(|(_, entry)| is_cube(entry))
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<String>>();
+            for name in names {
+                let entry = imported.remove(&name).unwrap();
+                cubes.push(cube_import(&name, &entry));
+            }
+        }
+        // the ISF v1 form lists the images with a `NAME` each
+        Some(serde_json::Value::Array(imported)) => {
+            imported.retain(|entry| {
+                if !is_cube(entry) {
+                    return true;
+                }
+                if let Some(name) = entry.get("NAME").and_then(|n| n.as_str()) {
+                    cubes.push(cube_import(name, entry));
+                }
+                false
+            });
+        }
+        _ => (),
+    }
+
+    if cubes.is_empty() {
+        return unchanged;
+    }
+
+    let num_lines = source[range.clone()].matches('\n').count();
+    let mut stripped = source[..range.start].to_string();
+    stripped.push_str(&header.to_string());
+    stripped.push_str(&"\n".repeat(num_lines));
+    stripped.push_str(&source[range.end..]);
+    (stripped, cubes)
+}
+
+#[cfg(test)]
+#[test]
+fn test_split_cube_imports() {
+    let source = "/*{\n\"IMPORTED\": {\n\"sky\": { \"TYPE\": \"cube\", \"PATH\": [\"a.png\", \"b.png\"] },\n\"noise\": { \"PATH\": \"noise.png\" }\n}\n}*/\nvoid main() {}\n";
+    let (stripped, cubes) = split_cube_imports(source);
+    assert_eq!(
+        cubes,
+        vec![CubeImport {
+            name: "sky".to_string(),
+            paths: vec![PathBuf::from("a.png"), PathBuf::from("b.png")],
+        }]
+    );
+    assert_eq!(stripped.lines().count(), source.lines().count());
+    assert!(stripped.ends_with("*/\nvoid main() {}\n"));
+
+    let isf = parse_isf(source).unwrap();
+    assert_eq!(isf.imported.keys().collect::<Vec<_>>(), vec!["noise"]);
+
+    let plain = "/*{ \"INPUTS\": [] }*/\nvoid main() {}\n";
+    assert_eq!(split_cube_imports(plain), (plain.to_string(), vec![]));
+}
+
+/// Parse an ISF header, leaving out cube maps.
+pub fn parse_isf(source: &str) -> Result<isf::Isf, isf::ParseError> {
+    isf::parse(&split_cube_imports(source).0)
+}
+
+/// The cube maps imported by the shader at the path.
+pub fn read_cube_imports(path: &Path) -> Vec<CubeImport> {
+    match std::fs::read_to_string(path) {
+        Ok(source) => split_cube_imports(&source).1,
+        Err(_) => vec![],
+    }
+}
+
+/// Find an `IMPORTED` file. Paths are relative to the shader as the spec says, the media
+/// directory is tried after that.
+pub fn imported_path(
+    shader_dir: &Path,
+    images_path: &Path,
+    path: &Path,
+) -> Result<PathBuf, String> {
+    [shader_dir, images_path]
+        .iter()
+        .map(|dir| dir.join(path))
+        .find(|p| p.exists())
+        .ok_or_else(|| {
+            format!(
+                "{} not found next to the shader ({}) or in the media directory ({})",
+                path.to_string_lossy(),
+                shader_dir.to_string_lossy(),
+                images_path.to_string_lossy()
+            )
+        })
 }
 
 pub fn split_result<T, E>(res: Result<T, E>) -> (Option<T>, Option<E>) {
@@ -0,0 +1,83 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::programs::isf::util;
+
+/// Read and check an ISF shader's JSON header, going further than parsing it does.
+/// Returns one message per problem.
+pub fn validate(path: &Path, images_path: &Path) -> Vec<String> {
+    let isf = match util::read_isf_from_path(path) {
+        Ok(isf) => isf,
+        Err(e) => return vec![format!("ISF: {}", e)],
+    };
+
+    let shader_dir = path.parent().unwrap_or(images_path);
+    let cubes = util::read_cube_imports(path);
+    let mut errors = vec![];
+    // inputs, imported images and pass targets all become uniforms so they share names
+    let mut names = HashSet::new();
+
+    for input in isf.inputs.iter() {
+        if !names.insert(input.name.as_str()) {
+            errors.push(format!("ISF: duplicate input '{}'", input.name));
+        }
+    }
+
+    for (name, image) in isf.imported.iter() {
+        if !names.insert(name.as_str()) {
+            errors.push(format!(
+                "ISF: imported image '{}' is already an input",
+                name
+            ));
+        }
+
+        if let Err(e) = util::imported_path(shader_dir, images_path, &image.path) {
+            errors.push(format!("ISF: imported image '{}': {}", name, e));
+        }
+    }
+
+    for cube in cubes.iter() {
+        if !names.insert(cube.name.as_str()) {
+            errors.push(format!(
+                "ISF: imported cube map '{}' is already used",
+                cube.name
+            ));
+        }
+
+        if cube.paths.len() != 6 {
+            errors.push(format!(
+                "ISF: imported cube map '{}' needs 6 faces, found {}",
+                cube.name,
+                cube.paths.len()
+            ));
+        }
+
+        for face in cube.paths.iter() {
+            if let Err(e) = util::imported_path(shader_dir, images_path, face) {
+                errors.push(format!("ISF: imported cube map '{}': {}", cube.name, e));
+            }
+        }
+    }
+
+    for (index, pass) in isf.passes.iter().enumerate() {
+        match &pass.target {
+            Some(target) => {
+                if !names.insert(target.as_str()) {
+                    errors.push(format!(
+                        "ISF: pass {} target '{}' is already used",
+                        index, target
+                    ));
+                }
+            }
+            None if pass.persistent => {
+                errors.push(format!(
+                    "ISF: pass {} is persistent but has no target",
+                    index
+                ));
+            }
+            None => (),
+        }
+    }
+
+    errors
+}
@@ -0,0 +1,165 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::library;
+use crate::programs::isf::data::ImageChoice;
+
+/// The value of an ISF input that's kept between launches.
+/// Images, audio and events aren't saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SavedValue {
+    Bool(bool),
+    Long(i32),
+    Float(f32),
+    Point2d([f32; 2]),
+    Color([f32; 4]),
+}
+
+/// Saved values of a shader's inputs by input name.
+pub type SavedInputs = HashMap<String, SavedValue>;
+
+/// Saved inputs by shader path.
+pub type SavedValues = HashMap<String, SavedInputs>;
+
+/// The sources picked for image inputs by shader path and input name.
+pub type SavedImages = HashMap<String, HashMap<String, ImageChoice>>;
+
+/// The key a shader's values are saved under.
+pub fn shader_key(path: &Path) -> String {
+    path.canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// A file in the user config directory.
+fn config_path(filename: &str) -> Option<PathBuf> {
+    Some(library::user_config_dir()?.join(filename))
+}
+
+fn read<T: DeserializeOwned + Default>(filename: &str) -> T {
+    let path = match config_path(filename) {
+        Some(p) if p.exists() => p,
+        _ => return T::default(),
+    };
+
+    let parsed = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| serde_json::from_str::<T>(&s).map_err(|e| e.to_string()));
+    match parsed {
+        Ok(values) => values,
+        Err(e) => {
+            println!("ignoring {}: {}", path.to_string_lossy(), e);
+            T::default()
+        }
+    }
+}
+
+fn write<T: Serialize>(filename: &str, values: &T) {
+    let path = match config_path(filename) {
+        Some(p) => p,
+        None => return,
+    };
+
+    let res = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .map_err(|e| e.to_string())
+        .and_then(|_| serde_json::to_string_pretty(values).map_err(|e| e.to_string()))
+        .and_then(|json| fs::write(&path, json).map_err(|e| e.to_string()));
+    if let Err(e) = res {
+        println!("failed to save {}: {}", path.to_string_lossy(), e);
+    }
+}
+
+/// Read the values saved by the last run from `isf-values.json`.
+pub fn load() -> SavedValues {
+    read("isf-values.json")
+}
+
+/// Write the values so they're restored on the next launch.
+pub fn save(values: &SavedValues) {
+    write("isf-values.json", values);
+}
+
+/// Read the image sources picked in earlier runs from `isf-images.json`.
+/// Files that have since been removed are dropped.
+pub fn load_images() -> SavedImages {
+    let mut images: SavedImages = read("isf-images.json");
+    for choices in images.values_mut() {
+        choices.retain(|_, choice| match choice {
+            ImageChoice::Image(path) | ImageChoice::Video(path) => path.exists(),
+            _ => true,
+        });
+    }
+    images
+}
+
+pub fn save_images(images: &SavedImages) {
+    write("isf-images.json", images);
+}
+
+#[cfg(test)]
+#[test]
+fn test_saved_values_json() {
+    let json = r#"{
+        "/shaders/isf/test_float.fs": {
+            "level": { "float": 0.5 },
+            "flip": { "bool": true },
+            "mode": { "long": 2 },
+            "center": { "point2d": [10.0, 20.0] },
+            "tint": { "color": [1.0, 0.5, 0.25, 1.0] }
+        }
+    }"#;
+    let values = serde_json::from_str::<SavedValues>(json).unwrap();
+    let inputs = &values["/shaders/isf/test_float.fs"];
+    assert_eq!(inputs["level"], SavedValue::Float(0.5));
+    assert_eq!(inputs["flip"], SavedValue::Bool(true));
+    assert_eq!(inputs["mode"], SavedValue::Long(2));
+    assert_eq!(inputs["center"], SavedValue::Point2d([10.0, 20.0]));
+    assert_eq!(inputs["tint"], SavedValue::Color([1.0, 0.5, 0.25, 1.0]));
+
+    let round_trip = serde_json::to_string(&values).unwrap();
+    assert_eq!(
+        serde_json::from_str::<SavedValues>(&round_trip).unwrap(),
+        values
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_saved_images_json() {
+    let json = r#"{
+        "/shaders/isf/test_image.fs": {
+            "inputImage": { "image": "/media/forest.jpg" },
+            "overlay": { "video": "/media/clip.mp4" },
+            "camera": "webcam",
+            "feedback": { "program": "warp" }
+        }
+    }"#;
+    let images = serde_json::from_str::<SavedImages>(json).unwrap();
+    let choices = &images["/shaders/isf/test_image.fs"];
+    assert_eq!(
+        choices["inputImage"],
+        ImageChoice::Image(PathBuf::from("/media/forest.jpg"))
+    );
+    assert_eq!(
+        choices["overlay"],
+        ImageChoice::Video(PathBuf::from("/media/clip.mp4"))
+    );
+    assert_eq!(choices["camera"], ImageChoice::Webcam);
+    assert_eq!(
+        choices["feedback"],
+        ImageChoice::Program("warp".to_string())
+    );
+
+    let round_trip = serde_json::to_string(&images).unwrap();
+    assert_eq!(
+        serde_json::from_str::<SavedImages>(&round_trip).unwrap(),
+        images
+    );
+}
@@ -1,28 +1,76 @@
 use nannou::prelude::*;
-use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use serde::Serialize;
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::mpsc::{channel, Receiver};
-use std::time;
+use std::path::{Path, PathBuf};
 
+use crate::library;
 use crate::programs::uniforms::base::Bufferable;
 use crate::util;
 
+mod cache;
+mod compat;
 mod config;
+mod discovery;
 pub mod isf;
 pub mod program;
 mod shaders;
 pub mod uniforms;
+mod validation;
+mod watcher;
+
+/// The result of compiling a single program, or loading a folder when `program` is `None`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CompileReport {
+    pub folder: String,
+    pub program: Option<String>,
+    pub errors: Vec<String>,
+}
+
+/// Every folder in the libraries with its programs, or the error it failed to load with.
+pub fn list_programs(
+    libraries: &[library::Library],
+) -> Result<Vec<(String, Result<Vec<String>, String>)>, String> {
+    let config = config::get_config(libraries)?;
+
+    Ok(config
+        .get_folder_names()
+        .into_iter()
+        .map(|name| {
+            let programs = match config.folders.get(&name) {
+                Some(folder_config) => Ok(folder_config.get_program_names()),
+                None => Err(config.broken_folders[&name].clone()),
+            };
+            (name, programs)
+        })
+        .collect())
+}
+
+fn print_problems(config: &config::Config) {
+    for (name, error) in config.broken_folders.iter() {
+        println!("folder '{}' failed to load: {}", name, error);
+    }
+    for diagnostic in config.diagnostics.iter() {
+        println!("{}", diagnostic);
+    }
+}
+
+/// A 2D value that's set by dragging on the canvas.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CanvasPoint {
+    /// A `point2D` input bound to the mouse, by chain position and input name.
+    Isf(usize, String),
+    /// One of the `points` uniforms by index.
+    Uniform(usize),
+}
 
 /// Stores GPU programs and related data.
 /// Manages the maintenance of shader programs.
-/// - listens to directory
+/// - listens to the shader and media directories of every library
 /// - compiles code
 /// - manages modules
 /// - handles errors
 /// - builds render pipelines
 /// - manages uniform buffers
-#[allow(dead_code)] // needed for shader_watcher
 pub struct ProgramStore {
     pub buffer_store: uniforms::BufferStore,
     pub current_subscriptions: Option<uniforms::UniformSubscriptions>,
@@ -30,63 +78,119 @@ pub struct ProgramStore {
     pub folder_index: usize,
     pub folder_names: Option<Vec<String>>,
     pub isf_pipeline: Option<isf::IsfPipeline>,
+    /// The stages of the running ISF program when it has a `chain`.
+    /// The linked stages are inside `isf_pipeline`.
+    pub isf_chain: Option<isf::chain::IsfChain>,
     pub isf_time: Option<isf::IsfTime>,
     pub program_names: Option<Vec<String>>,
     pub program_index: usize,
 
-    changes_channel: Receiver<DebouncedEvent>,
     config: Option<config::Config>,
+    current_key: Option<cache::ProgramKey>,
     current_program: Option<program::Program>,
-    shader_watcher: notify::PollWatcher,
+    program_cache: cache::ProgramCache,
+    file_watcher: watcher::FileWatcher,
+    libraries: Vec<library::Library>,
+    /// The sources picked for ISF image inputs in the controls, by shader key and input name.
+    picked_isf_images: isf::values::SavedImages,
+    /// The last used ISF input values, written to disk when programs are switched and on exit.
+    isf_values: isf::values::SavedValues,
     render_texture: wgpu::Texture,
     texture_reshaper: wgpu::TextureReshaper,
 }
 
 impl ProgramStore {
-    pub fn new(app: &App, device: &wgpu::Device, size: Vector2<f32>, num_samples: u32) -> Self {
+    pub fn new(
+        libraries: Vec<library::Library>,
+        device: &wgpu::Device,
+        size: Vector2<f32>,
+        num_samples: u32,
+    ) -> Self {
         let buffer_store = uniforms::BufferStore::new(device, size);
 
-        // setup shader watcher
-        let (send_channel, changes_channel) = channel();
-        let mut shader_watcher =
-            notify::PollWatcher::new(send_channel, time::Duration::from_secs(1)).unwrap();
-        let shader_path = util::shaders_path_string(app);
-        shader_watcher
-            .watch(shader_path.as_str(), RecursiveMode::Recursive)
-            .unwrap();
+        // setup file watcher
+        let watched = libraries
+            .iter()
+            .flat_map(|l| vec![l.shaders_path.clone(), l.media_path.clone()])
+            .filter(|p| p.exists())
+            .collect::<Vec<PathBuf>>();
+        let file_watcher = watcher::FileWatcher::new(&watched);
+        if !file_watcher.is_native() {
+            println!("watching for changes by polling");
+        }
 
         let render_texture = util::create_app_texture(device, size, num_samples);
         let texture_reshaper = util::create_texture_reshaper(device, &render_texture, num_samples);
 
         Self {
             buffer_store,
-            changes_channel,
             config: None,
+            current_key: None,
             current_program: None,
             current_subscriptions: None,
             error: None,
             folder_index: 0,
             folder_names: None,
             isf_pipeline: None,
+            isf_chain: None,
             isf_time: None,
+            program_cache: cache::ProgramCache::new(cache::DEFAULT_CAPACITY),
             program_index: 0,
             program_names: None,
-            shader_watcher,
+            file_watcher,
+            libraries,
+            picked_isf_images: isf::values::load_images(),
+            isf_values: isf::values::load(),
             render_texture,
             texture_reshaper,
         }
     }
 
+    /// Shaders are compiled and imported relative to the primary library.
+    fn shaders_path(&self) -> PathBuf {
+        self.libraries[0].shaders_path.clone()
+    }
+
+    /// The library a folder was loaded from.
+    fn folder_library(&self, folder_name: &str) -> &library::Library {
+        let index = self
+            .config
+            .as_ref()
+            .and_then(|c| c.folders.get(folder_name))
+            .map_or(0, |f| f.library);
+        &self.libraries[index]
+    }
+
+    fn is_media_path(&self, path: &Path) -> bool {
+        self.libraries
+            .iter()
+            .any(|l| path.starts_with(&l.media_path))
+    }
+
     fn get_folder_name(&self) -> Option<String> {
         let folder_names = &self.folder_names.as_ref()?;
         Some(folder_names[self.folder_index].clone())
     }
 
+    /// The name shown for a folder in the folder menu.
+    pub fn folder_label(&self, name: &str) -> String {
+        match &self.config {
+            Some(config) => config.folder_label(name),
+            None => name.to_string(),
+        }
+    }
+
     fn get_program_name(&self) -> Option<String> {
         let program_names = &self.program_names.as_ref()?;
         Some(program_names[self.program_index].clone())
     }
 
+    /// The folder and name of the running program.
+    pub fn running_program(&self) -> Option<(String, String)> {
+        let key = self.current_key.as_ref()?;
+        Some((key.folder.clone(), key.program.clone()))
+    }
+
     /// Create the render pipeline with the program's required buffers
     fn create_render_pipeline(&mut self, device: &wgpu::Device, num_samples: u32) {
         let current_program = match &mut self.current_program {
@@ -118,7 +222,7 @@ impl ProgramStore {
 
     /// Compile current program with latest shader code.
     /// Call once after initialization.
-    fn compile_current(&mut self, app: &App, device: &wgpu::Device, num_samples: u32) {
+    fn compile_current(&mut self, device: &wgpu::Device, num_samples: u32) {
         let current_program = match &mut self.current_program {
             Some(p) => p,
             None => {
@@ -126,50 +230,417 @@ impl ProgramStore {
             }
         };
 
-        current_program.compile(app, device);
+        current_program.compile(device, &self.program_cache.spirv_cache());
+        current_program.validate_bindings(&self.buffer_store.buffers);
         self.create_render_pipeline(device, num_samples);
     }
 
-    fn configure_isf_program(
-        &mut self,
-        app: &App,
+    /// The sources for an ISF program's image inputs.
+    /// Sources picked in the controls take precedence over the ones in `index.json`.
+    fn isf_image_choices(
+        &self,
+        folder_name: &str,
+        program_config: &config::ProgramConfig,
+        fs_path: &Path,
+    ) -> HashMap<String, isf::data::ImageChoice> {
+        let media_path = &self.folder_library(folder_name).media_path;
+        let mut choices = program_config
+            .images
+            .iter()
+            .flatten()
+            .map(|(input_name, source)| {
+                let choice = match source {
+                    config::IsfImageSource::Image(path) => {
+                        isf::data::ImageChoice::Image(media_path.join(path))
+                    }
+                    config::IsfImageSource::Video(path) => {
+                        isf::data::ImageChoice::Video(media_path.join(path))
+                    }
+                    config::IsfImageSource::Webcam => isf::data::ImageChoice::Webcam,
+                    config::IsfImageSource::Program(name) => {
+                        isf::data::ImageChoice::Program(name.clone())
+                    }
+                };
+                (input_name.clone(), choice)
+            })
+            .collect::<HashMap<String, isf::data::ImageChoice>>();
+
+        if let Some(picked) = self
+            .picked_isf_images
+            .get(&isf::values::shader_key(fs_path))
+        {
+            choices.extend(picked.clone());
+        }
+
+        choices
+    }
+
+    /// What image inputs can be set to in the folder.
+    fn isf_image_options(&self, folder_name: &str) -> isf::data::ImageOptions {
+        let media_path = &self.folder_library(folder_name).media_path;
+        let images = isf::data::image_paths(media_path)
+            .map(|path| {
+                let label = path
+                    .strip_prefix(media_path)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .to_string();
+                (label, path)
+            })
+            .collect();
+
+        let mut programs = match self
+            .config
+            .as_ref()
+            .and_then(|c| c.folders.get(folder_name))
+        {
+            Some(folder) => folder
+                .programs
+                .iter()
+                .filter(|(_, p)| p.isf.unwrap_or(false))
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<String>>(),
+            None => vec![],
+        };
+        programs.sort();
+
+        isf::data::ImageOptions { images, programs }
+    }
+
+    fn create_isf_pipeline(
+        &self,
         device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
         program_config: &config::ProgramConfig,
-        folder_name: String,
+        folder_name: &str,
         num_samples: u32,
         size: Point2,
-    ) {
-        let shader_path = app
-            .project_path()
-            .unwrap()
-            .join("shaders")
-            .join(folder_name)
-            .join(program_config.pipeline.frag.clone());
+    ) -> isf::IsfPipeline {
+        let folder_path = self.shaders_path().join(folder_name);
+        let shader_path = folder_path.join(program_config.pipeline.frag.clone());
+        let vs_path = isf::shader::vertex_path(
+            &folder_path,
+            &program_config.pipeline.frag,
+            program_config.pipeline.vert.as_deref(),
+        );
 
-        let media_path = app.project_path().unwrap().join("media");
+        let media_path = self.folder_library(folder_name).media_path.clone();
+        let image_choices = self.isf_image_choices(folder_name, program_config, &shader_path);
 
-        let isf_pipeline = isf::IsfPipeline::new(
+        let mut isf_pipeline = isf::IsfPipeline::new(
             device,
             encoder,
-            None,
+            vs_path,
             shader_path,
             Frame::TEXTURE_FORMAT,
             [size[0] as u32, size[1] as u32],
             num_samples,
             &media_path,
             num_samples,
+            self.shaders_path(),
+            program_config.defines.clone().unwrap_or_default(),
+            self.program_cache.spirv_cache(),
+            image_choices,
+        );
+        isf_pipeline.restore_values(&self.isf_values);
+        isf_pipeline
+    }
+
+    /// Write the running ISF program's input values and picked images to disk so they're
+    /// restored next time.
+    pub fn save_isf_values(&mut self) {
+        if let Some(isf_pipeline) = &self.isf_pipeline {
+            isf_pipeline.save_values(&mut self.isf_values);
+        }
+        if let Some(isf_chain) = &self.isf_chain {
+            isf_chain.save_values(&mut self.isf_values);
+        }
+        isf::values::save(&self.isf_values);
+        isf::values::save_images(&self.picked_isf_images);
+    }
+
+    /// Render another ISF program in the folder into an image input of the pipeline.
+    /// Its own image inputs don't take program sources, so programs can't feed each other.
+    fn attach_isf_program(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        isf_pipeline: &mut isf::IsfPipeline,
+        folder_name: &str,
+        input_name: &str,
+        program_name: &str,
+    ) {
+        let program_config = match self
+            .config
+            .as_ref()
+            .and_then(|c| c.program(folder_name, program_name))
+        {
+            Some(c) if c.isf.unwrap_or(false) => c.clone(),
+            _ => {
+                println!(
+                    "Can't use '{}' for {}, it isn't an ISF program in {}",
+                    program_name, input_name, folder_name
+                );
+                return;
+            }
+        };
+
+        let [width, height] = isf_pipeline.dst_texture_size();
+        let pipeline = self.create_isf_pipeline(
+            device,
+            encoder,
+            &program_config,
+            folder_name,
+            1,
+            pt2(width as f32, height as f32),
+        );
+        isf_pipeline.set_program_source(input_name, program_name, pipeline);
+    }
+
+    fn configure_isf_program(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        program_config: &config::ProgramConfig,
+        folder_name: String,
+        program_name: String,
+        num_samples: u32,
+        size: Point2,
+    ) {
+        let mut isf_pipeline = self.create_isf_pipeline(
+            device,
+            encoder,
+            program_config,
+            &folder_name,
+            num_samples,
+            size,
         );
 
+        for (input_name, choice) in isf_pipeline.image_choices.clone() {
+            if let isf::data::ImageChoice::Program(program_name) = choice {
+                self.attach_isf_program(
+                    device,
+                    encoder,
+                    &mut isf_pipeline,
+                    &folder_name,
+                    &input_name,
+                    &program_name,
+                );
+            }
+        }
+        isf_pipeline.image_options = self.isf_image_options(&folder_name);
+
+        // link the filters after the program
+        self.isf_chain = None;
+        let filters = program_config.chain.clone().unwrap_or_default();
+        let isf_pipeline = if filters.is_empty() {
+            Some(isf_pipeline)
+        } else {
+            let mut stages = vec![(program_name, isf_pipeline)];
+            for filter_name in filters {
+                let filter_config = match self
+                    .config
+                    .as_ref()
+                    .and_then(|c| c.program(&folder_name, &filter_name))
+                {
+                    Some(c) => c.clone(),
+                    None => continue,
+                };
+                let mut filter = self.create_isf_pipeline(
+                    device,
+                    encoder,
+                    &filter_config,
+                    &folder_name,
+                    1,
+                    size,
+                );
+                filter.image_options = self.isf_image_options(&folder_name);
+                stages.push((filter_name, filter));
+            }
+
+            let (chain, last) = isf::chain::IsfChain::new(device, encoder, stages, num_samples);
+            self.isf_chain = Some(chain);
+            last
+        };
+
         let isf_time = Default::default();
 
-        self.isf_pipeline = Some(isf_pipeline);
+        self.isf_pipeline = isf_pipeline;
         self.isf_time = Some(isf_time);
         self.error = None;
         self.current_program = None;
         self.current_subscriptions = None;
     }
 
+    /// The number of ISF pipelines drawn each frame, more than one when a chain is running.
+    pub fn isf_stage_count(&self) -> usize {
+        match &self.isf_chain {
+            Some(chain) => chain.num_linked(),
+            None => 1,
+        }
+    }
+
+    /// Set the source of an image input of the running ISF program.
+    /// `stage` is the position of the program in the chain, 0 without a chain.
+    /// The choice is saved for the shader between launches.
+    pub fn set_isf_image(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        stage: usize,
+        input_name: &str,
+        choice: isf::data::ImageChoice,
+    ) {
+        // the chain feeds the inputs of the stages after the first
+        if stage > 0 && input_name == isf::chain::CHAIN_INPUT {
+            return;
+        }
+
+        let num_linked = self.isf_stage_count();
+        let (mut last, folder_name) = match (self.isf_pipeline.take(), self.get_folder_name()) {
+            (Some(p), Some(f)) => (p, f),
+            (isf_pipeline, _) => {
+                self.isf_pipeline = isf_pipeline;
+                return;
+            }
+        };
+
+        let isf_pipeline = match isf::chain::linked_stage_mut(&mut last, num_linked, stage) {
+            Some(p) => p,
+            None => {
+                self.isf_pipeline = Some(last);
+                return;
+            }
+        };
+
+        if let Some(fs_path) = isf_pipeline.fs_path() {
+            self.picked_isf_images
+                .entry(isf::values::shader_key(fs_path))
+                .or_default()
+                .insert(input_name.to_string(), choice.clone());
+        }
+
+        match choice {
+            isf::data::ImageChoice::Program(program_name) => self.attach_isf_program(
+                device,
+                encoder,
+                isf_pipeline,
+                &folder_name,
+                input_name,
+                &program_name,
+            ),
+            choice => isf_pipeline.set_image_choice(input_name, choice),
+        }
+
+        self.isf_pipeline = Some(last);
+    }
+
+    /// Turn a stage of the running chain on or off.
+    pub fn toggle_isf_stage(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        index: usize,
+    ) {
+        if let (Some(chain), Some(last)) = (self.isf_chain.as_mut(), self.isf_pipeline.take()) {
+            self.isf_pipeline = chain.toggle(device, encoder, last, index);
+        }
+    }
+
+    /// Move a stage of the running chain one place earlier.
+    pub fn move_isf_stage_up(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        index: usize,
+    ) {
+        if let (Some(chain), Some(last)) = (self.isf_chain.as_mut(), self.isf_pipeline.take()) {
+            self.isf_pipeline = chain.move_up(device, encoder, last, index);
+        }
+    }
+
+    /// The size of the canvas points are positioned on, the ISF render size or the window size.
+    pub fn canvas_size(&self) -> Point2 {
+        match &self.isf_pipeline {
+            Some(isf_pipeline) => {
+                let [w, h] = isf_pipeline.dst_texture_size();
+                pt2(w as f32, h as f32)
+            }
+            None => self.buffer_store.general_uniforms.data.resolution,
+        }
+    }
+
+    /// The points that can be dragged on the canvas, in canvas pixels from the bottom left.
+    pub fn canvas_points(&mut self) -> Vec<(CanvasPoint, Point2)> {
+        let num_linked = self.isf_stage_count();
+        if let Some(isf_pipeline) = &mut self.isf_pipeline {
+            let mut points = vec![];
+            isf::chain::for_each_linked(isf_pipeline, num_linked, &mut |stage, p| {
+                for (name, point) in p.mouse_point_values() {
+                    points.push((CanvasPoint::Isf(stage, name), point));
+                }
+            });
+            return points;
+        }
+
+        match &self.current_subscriptions {
+            Some(subscriptions) if subscriptions.points => (),
+            _ => return vec![],
+        }
+
+        let point_uniforms = &self.buffer_store.point_uniforms;
+        (0..point_uniforms.names.len())
+            .filter_map(|i| Some((CanvasPoint::Uniform(i), point_uniforms.get(i)?)))
+            .collect()
+    }
+
+    /// The point closest to a position on the canvas.
+    pub fn nearest_canvas_point(&mut self, position: Point2) -> Option<CanvasPoint> {
+        let distance = |p: &Point2| (p.x - position.x).powi(2) + (p.y - position.y).powi(2);
+        self.canvas_points()
+            .into_iter()
+            .min_by(|(_, a), (_, b)| distance(a).partial_cmp(&distance(b)).unwrap())
+            .map(|(point, _)| point)
+    }
+
+    /// Move a point to a position on the canvas.
+    pub fn set_canvas_point(&mut self, point: &CanvasPoint, position: Point2) {
+        match point {
+            CanvasPoint::Isf(stage, input_name) => {
+                let num_linked = self.isf_stage_count();
+                if let Some(last) = &mut self.isf_pipeline {
+                    if let Some(p) = isf::chain::linked_stage_mut(last, num_linked, *stage) {
+                        p.set_point(input_name, position);
+                    }
+                }
+            }
+            CanvasPoint::Uniform(index) => self.buffer_store.point_uniforms.set(*index, position),
+        }
+    }
+
+    /// Move the running program into the cache so it can be resumed later.
+    fn stash_current(&mut self) {
+        let key = match self.current_key.take() {
+            Some(k) => k,
+            None => {
+                self.current_program = None;
+                self.isf_pipeline = None;
+                self.isf_chain = None;
+                return;
+            }
+        };
+
+        if let Some(isf_pipeline) = self.isf_pipeline.take() {
+            self.program_cache.insert(
+                key,
+                cache::CachedProgram::Isf(isf_pipeline, self.isf_chain.take()),
+            );
+        } else if let Some(current_program) = self.current_program.take() {
+            self.program_cache
+                .insert(key, cache::CachedProgram::Program(current_program));
+        }
+    }
+
     fn configure_program(
         &mut self,
         app: &App,
@@ -177,6 +648,7 @@ impl ProgramStore {
         encoder: &mut wgpu::CommandEncoder,
         program_config: &config::ProgramConfig,
         folder_name: String,
+        program_name: String,
         num_samples: u32,
         size: Point2,
     ) {
@@ -187,27 +659,73 @@ impl ProgramStore {
         if let Some(ref mut isf_pipeline) = self.isf_pipeline {
             isf_pipeline.end_session();
         }
+        if self.isf_pipeline.is_some() {
+            self.save_isf_values();
+        }
+
+        // keep the old program around and look for a compiled version of the new one
+        self.stash_current();
+
+        // a program with config errors may reference uniforms that don't exist
+        let config_errors = self
+            .config
+            .as_ref()
+            .and_then(|c| c.program_errors(&folder_name, &program_name));
+        if let Some(errors) = config_errors {
+            self.error = Some(errors);
+            self.isf_pipeline = None;
+            self.isf_chain = None;
+            self.isf_time = None;
+            self.current_subscriptions = None;
+            return;
+        }
+        let key = cache::ProgramKey::new(folder_name.clone(), program_name.clone());
+        let cached = self.program_cache.take(&key);
+        self.current_key = Some(key);
 
         if let Some(isf) = program_config.isf {
             if isf {
-                self.configure_isf_program(
-                    app,
-                    device,
-                    encoder,
-                    program_config,
-                    folder_name,
-                    num_samples,
-                    size,
-                );
+                match cached {
+                    Some(cache::CachedProgram::Isf(mut isf_pipeline, isf_chain))
+                        if isf_pipeline.dst_texture_size() == [size[0] as u32, size[1] as u32] =>
+                    {
+                        isf_pipeline.start_session();
+                        self.isf_pipeline = Some(isf_pipeline);
+                        self.isf_chain = isf_chain;
+                        self.isf_time = Some(Default::default());
+                        self.error = None;
+                        self.current_program = None;
+                        self.current_subscriptions = None;
+                    }
+                    _ => self.configure_isf_program(
+                        device,
+                        encoder,
+                        program_config,
+                        folder_name,
+                        program_name,
+                        num_samples,
+                        size,
+                    ),
+                }
                 return;
             }
         }
 
         self.isf_pipeline = None;
+        self.isf_chain = None;
         self.isf_time = None;
 
-        // create current program
-        let current_program = program::Program::new(program_config.clone(), folder_name);
+        // reuse the compiled program if there is one, otherwise create it
+        let cached_program = match cached {
+            Some(cache::CachedProgram::Program(p)) => Some(p),
+            _ => None,
+        };
+        let compiled = cached_program.is_some();
+        let media_path = self.folder_library(&folder_name).media_path.clone();
+        let shaders_path = self.shaders_path();
+        let current_program = cached_program.unwrap_or_else(|| {
+            program::Program::new(program_config.clone(), folder_name, shaders_path)
+        });
         self.current_program = Some(current_program);
 
         // get subscriptions and initialize
@@ -217,6 +735,7 @@ impl ProgramStore {
             app,
             device,
             encoder,
+            &media_path,
             &current_subscriptions,
             &program_config.config,
             size,
@@ -224,10 +743,73 @@ impl ProgramStore {
         );
 
         self.current_subscriptions = Some(current_subscriptions);
-        self.compile_current(app, device, num_samples);
+        if compiled {
+            self.create_render_pipeline(device, num_samples);
+            self.buffer_store.finish_update();
+        } else {
+            self.compile_current(device, num_samples);
+        }
         self.error = None;
     }
 
+    /// Compile the shaders of every program in the current folder in the background
+    /// so that switching between them doesn't stall the render loop.
+    fn prewarm_folder(&self) {
+        let config = match &self.config {
+            Some(c) if c.prewarm => c,
+            _ => return,
+        };
+
+        let folder_name = match self.get_folder_name() {
+            Some(n) => n,
+            None => return,
+        };
+
+        let folder_config = match config.folders.get(&folder_name) {
+            Some(c) => c,
+            None => return,
+        };
+
+        let shaders_path = self.shaders_path();
+        let mut jobs = vec![];
+
+        for program_config in folder_config.programs.values() {
+            let defines = program_config.defines.clone().unwrap_or_default();
+
+            if program_config.isf.unwrap_or(false) {
+                let folder_path = shaders_path.join(&folder_name);
+                jobs.push(cache::PrewarmJob::Isf {
+                    path: folder_path.join(&program_config.pipeline.frag),
+                    vs_path: isf::shader::vertex_path(
+                        &folder_path,
+                        &program_config.pipeline.frag,
+                        program_config.pipeline.vert.as_deref(),
+                    ),
+                    imports_path: shaders_path.clone(),
+                    defines,
+                });
+                continue;
+            }
+
+            let (vert_name, frag_name) = program::shader_filenames(program_config, &folder_name);
+            let frontend = compat::Frontend::from_config(program_config);
+            for (filename, frontend) in [(vert_name, None), (frag_name, frontend)].iter() {
+                // WGSL isn't compiled to SPIR-V ahead of time
+                if shaders::is_wgsl(filename) {
+                    continue;
+                }
+                jobs.push(cache::PrewarmJob::Glsl {
+                    shaders_path: shaders_path.clone(),
+                    filename: filename.clone(),
+                    frontend: frontend.clone(),
+                    defines: defines.clone(),
+                });
+            }
+        }
+
+        self.program_cache.prewarm(jobs);
+    }
+
     /// Read fresh config and recompile
     pub fn configure(
         &mut self,
@@ -237,12 +819,14 @@ impl ProgramStore {
         num_samples: u32,
         size: Point2,
     ) {
-        // first, clear the current program
+        // first, clear the current program and anything compiled with the old config
         if let Some(current_program) = &mut self.current_program {
             current_program.clear();
         }
+        self.current_key = None;
+        self.program_cache.clear();
 
-        let config = match config::get_config(app) {
+        let mut config = match config::get_config(&self.libraries) {
             Ok(c) => c,
             Err(e) => {
                 self.error = Some(e);
@@ -250,6 +834,11 @@ impl ProgramStore {
             }
         };
 
+        if let Some(previous) = &self.config {
+            config.keep_broken_folders(previous);
+        }
+        print_problems(&config);
+
         self.config = Some(config.clone());
         let folder_names = config.get_folder_names();
 
@@ -286,41 +875,33 @@ impl ProgramStore {
 
         let mut folder_name = folder_name_opt.unwrap();
 
-        let missing_folder = format!("Missing default folder config '{}'", config.default);
+        // read the folder config, try the default folder and then any folder that loaded on failure
+        if !config.folders.contains_key(&folder_name) {
+            let default_index = config
+                .get_default_folder_index(&folder_names)
+                .ok()
+                .filter(|i| config.folders.contains_key(&folder_names[*i]));
+            let loaded_index = folder_names
+                .iter()
+                .position(|n| config.folders.contains_key(n));
 
-        // read the folder config, try the default folder on failure
-        let folder_config = match config.folders.get(&folder_name) {
-            Some(c) => c,
-            None => {
-                // we already tried the new config, abort
-                if using_defaults {
-                    self.error = Some(missing_folder);
-                    return;
+            match default_index.or(loaded_index) {
+                Some(i) => {
+                    self.folder_index = i;
+                    folder_name = folder_names[i].clone();
+                    using_defaults = true;
                 }
-
-                // maybe the config updated, get the new default index
-                let folder_index = match config.get_default_folder_index(&folder_names) {
-                    Ok(i) => i,
-                    Err(e) => {
-                        self.error = Some(e);
-                        return;
-                    }
-                };
-
-                self.folder_index = folder_index;
-                folder_name = folder_names[folder_index].clone();
-                using_defaults = true;
-
-                // retry
-                match config.folders.get(&folder_name) {
-                    Some(c) => c,
-                    None => {
-                        self.error = Some(missing_folder);
-                        return;
+                None => {
+                    let mut error = "No folders could be loaded".to_string();
+                    for (name, e) in config.broken_folders.iter() {
+                        error.push_str(&format!("\n{}: {}", name, e));
                     }
+                    self.error = Some(error);
+                    return;
                 }
             }
-        };
+        }
+        let folder_config = &config.folders[&folder_name];
 
         let program_names = folder_config.get_program_names();
         let mut program_name_opt: Option<String> = None;
@@ -399,19 +980,142 @@ impl ProgramStore {
             encoder,
             program_config,
             folder_name,
+            program_name,
             num_samples,
             size,
         );
+        self.prewarm_folder();
     }
 
-    fn path_changed(&mut self) -> Option<PathBuf> {
-        match self.changes_channel.try_recv() {
-            Ok(event) => match event {
-                DebouncedEvent::Write(path) => Some(path),
-                _ => None,
-            },
-            _ => None,
+    /// Use a new config while keeping the selected folder and program.
+    fn set_config(&mut self, config: config::Config, folder_name: &str, program_name: &str) {
+        let folder_names = config.get_folder_names();
+        self.folder_index = folder_names
+            .iter()
+            .position(|n| n == folder_name)
+            .unwrap_or(0);
+        self.folder_names = Some(folder_names);
+
+        self.program_names = config
+            .folders
+            .get(folder_name)
+            .map(|f| f.get_program_names());
+        self.program_index = self
+            .program_names
+            .as_ref()
+            .and_then(|names| names.iter().position(|n| n == program_name))
+            .unwrap_or(0);
+
+        self.config = Some(config);
+    }
+
+    /// Read fresh config and only rebuild what changed.
+    fn reload_config(
+        &mut self,
+        app: &App,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        num_samples: u32,
+        size: Point2,
+    ) {
+        let (old_config, key) = match (&self.config, &self.current_key) {
+            (Some(c), Some(k)) if self.error.is_none() => (c.clone(), k.clone()),
+            _ => return self.configure(app, device, encoder, num_samples, size),
+        };
+
+        let mut config = match config::get_config(&self.libraries) {
+            Ok(c) => c,
+            Err(e) => {
+                self.error = Some(e);
+                return;
+            }
+        };
+        config.keep_broken_folders(&old_config);
+
+        let old_program_config = old_config.program(&key.folder, &key.program).cloned();
+        let program_config = match config.program(&key.folder, &key.program) {
+            Some(c) if config.program_errors(&key.folder, &key.program).is_none() => c.clone(),
+            _ => return self.configure(app, device, encoder, num_samples, size),
+        };
+
+        print_problems(&config);
+
+        // compiled programs whose entry changed are stale
+        self.program_cache.retain(|k| {
+            old_config.program(&k.folder, &k.program) == config.program(&k.folder, &k.program)
+        });
+
+        self.set_config(config, &key.folder, &key.program);
+
+        let old_program_config = match old_program_config {
+            Some(c) if c == program_config => return,
+            Some(c) => c,
+            None => return self.configure(app, device, encoder, num_samples, size),
+        };
+
+        let same_uniforms = old_program_config.uniforms == program_config.uniforms
+            && old_program_config.config == program_config.config;
+        if same_uniforms && self.current_program.is_some() && !program_config.isf.unwrap_or(false) {
+            self.current_program = Some(program::Program::new(
+                program_config,
+                key.folder,
+                self.shaders_path(),
+            ));
+            self.compile_current(device, num_samples);
+            return;
         }
+
+        // the running program is outdated so it mustn't be cached
+        self.current_key = None;
+        self.configure_program(
+            app,
+            device,
+            encoder,
+            &program_config,
+            key.folder,
+            key.program,
+            num_samples,
+            size,
+        );
+    }
+
+    /// Reload or unload media used by the current program.
+    fn update_media(
+        &mut self,
+        app: &App,
+        device: &wgpu::Device,
+        path: &Path,
+        change: watcher::Change,
+    ) {
+        let filepath = path.to_string_lossy();
+        match change {
+            watcher::Change::Removed => self
+                .buffer_store
+                .image_uniforms
+                .media_removed(device, &filepath),
+            _ => self
+                .buffer_store
+                .image_uniforms
+                .media_changed(app, &filepath),
+        }
+    }
+
+    /// Whether a new or deleted file should update the folder and program menus.
+    fn is_new_program_file(&self, path: &Path) -> bool {
+        let is_program_ext = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => ["frag", "fs", "wgsl"].contains(&ext),
+            None => false,
+        };
+
+        let known = match &self.config {
+            Some(config) => config
+                .program_paths(&self.shaders_path())
+                .iter()
+                .any(|p| p == path),
+            None => false,
+        };
+
+        is_program_ext && !known
     }
 
     /// Check if changes have been made to shaders and recompile if needed.
@@ -423,23 +1127,49 @@ impl ProgramStore {
         encoder: &mut wgpu::CommandEncoder,
         size: Point2,
         num_samples: u32,
-        path_changed: Option<PathBuf>,
+        changes: &watcher::Changes,
     ) {
-        if let Some(path) = path_changed {
-            let path_str = path.into_os_string().into_string().unwrap();
-            println!("changes written to: {}", path_str);
-
-            if path_str.ends_with(".json") {
-                self.configure(app, device, encoder, num_samples, size);
-            } else {
-                self.compile_current(app, device, num_samples);
+        let mut reconfigure = changes.rescan;
+        let mut recompile = false;
+
+        for (path, change) in changes.files.iter() {
+            println!("{:?}: {}", change, path.to_string_lossy());
+
+            if self.is_media_path(path) {
+                self.update_media(app, device, path, *change);
+                continue;
+            }
+
+            if path.extension().map_or(false, |ext| ext == "json") {
+                reconfigure = true;
+                continue;
+            }
+
+            // only recompile programs that use the file
+            self.program_cache.invalidate_path(path);
+
+            let is_dependency = match &self.current_program {
+                Some(current_program) => current_program.dependencies().contains(path),
+                None => false,
+            };
+
+            if is_dependency {
+                recompile = true;
+            } else if *change != watcher::Change::Modified && self.is_new_program_file(path) {
+                reconfigure = true;
             }
         }
 
+        if reconfigure {
+            self.reload_config(app, device, encoder, num_samples, size);
+        } else if recompile {
+            self.compile_current(device, num_samples);
+        }
+
         if let Some(current_program) = &mut self.current_program {
             if current_program.is_new() {
                 // if the shader has changed recompile and recreate the pipeline
-                self.compile_current(app, device, num_samples);
+                self.compile_current(device, num_samples);
                 self.buffer_store.finish_update();
             } else if self.buffer_store.updated() {
                 // if the data has changed only just recreated the pipeline
@@ -474,26 +1204,163 @@ impl ProgramStore {
         size: Point2,
         num_samples: u32,
     ) {
-        let path_changed = self.path_changed();
+        let changes = self.file_watcher.changes();
+
+        let images_path = match self.get_folder_name() {
+            Some(folder_name) => self.folder_library(&folder_name).media_path.clone(),
+            None => self.libraries[0].media_path.clone(),
+        };
 
         if let Some(isf_pipeline) = self.isf_pipeline.as_mut() {
-            let mut touched: Vec<String> = vec![];
-            if let Some(path) = path_changed.clone() {
-                touched.push(String::from(path.to_str().unwrap()));
-            }
+            let touched = changes.paths().cloned().collect::<Vec<PathBuf>>();
 
-            let images_path = app.project_path().unwrap().join("media");
             isf_pipeline.encode_update(device, encoder, &images_path, touched, num_samples);
 
             if let Some(isf_time) = self.isf_time.as_mut() {
-                isf_time.time = update.since_start.secs() as _;
-                isf_time.time_delta = update.since_last.secs() as _;
+                isf_time.advance(update.since_last.secs() as _);
             }
         } else {
             self.update_uniforms(device, encoder, size, num_samples);
         }
 
-        self.update_shaders(app, device, encoder, size, num_samples, path_changed);
+        self.update_shaders(app, device, encoder, size, num_samples, &changes);
+    }
+
+    /// Select a folder and then a program by name, either can be left out to keep the default.
+    pub fn select_by_name(
+        &mut self,
+        app: &App,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        folder: Option<&str>,
+        program: Option<&str>,
+        size: Point2,
+        num_samples: u32,
+    ) -> Result<(), String> {
+        if let Some(folder) = folder {
+            let index = self
+                .folder_names
+                .as_ref()
+                .and_then(|names| names.iter().position(|n| n == folder))
+                .ok_or_else(|| format!("Unknown folder '{}'", folder))?;
+            self.select_folder(app, device, encoder, index, size, num_samples);
+        }
+
+        if let Some(program) = program {
+            let index = self
+                .program_names
+                .as_ref()
+                .and_then(|names| names.iter().position(|n| n == program))
+                .ok_or_else(|| format!("Unknown program '{}'", program))?;
+            self.select_program(app, device, encoder, index, true, size, num_samples);
+        }
+
+        Ok(())
+    }
+
+    /// Compile a program's shaders without running it, returning its errors.
+    /// ISF headers are validated as well.
+    fn compile_program(
+        &self,
+        device: &wgpu::Device,
+        program_config: &config::ProgramConfig,
+        folder_name: &str,
+        media_path: &Path,
+    ) -> Vec<String> {
+        let spirv_cache = self.program_cache.spirv_cache();
+
+        if program_config.isf.unwrap_or(false) {
+            let folder_path = self.shaders_path().join(folder_name);
+            let path = folder_path.join(&program_config.pipeline.frag);
+            let vs_path = isf::shader::vertex_path(
+                &folder_path,
+                &program_config.pipeline.frag,
+                program_config.pipeline.vert.as_deref(),
+            );
+            let mut errors = isf::validation::validate(&path, media_path);
+
+            let defines = program_config.defines.clone().unwrap_or_default();
+            if let Some(ref vs_path) = vs_path {
+                let (_, error) = isf::shader::compile_isf_vertex_shader(
+                    device,
+                    vs_path,
+                    &path,
+                    &self.shaders_path(),
+                    &defines,
+                    &spirv_cache,
+                );
+                errors.extend(error.map(|e| e.to_string()));
+            }
+            let (_, error) = isf::shader::compile_isf_shader(
+                device,
+                &path,
+                vs_path.as_deref(),
+                &self.shaders_path(),
+                &defines,
+                &spirv_cache,
+            );
+            errors.extend(error.map(|e| e.to_string()));
+            return errors;
+        }
+
+        let mut program = program::Program::new(
+            program_config.clone(),
+            folder_name.to_string(),
+            self.shaders_path(),
+        );
+        program.compile(device, &spirv_cache);
+        program.validate_bindings(&self.buffer_store.buffers);
+
+        let mut errors = program
+            .errors
+            .iter()
+            .map(|(filename, e)| format!("{}: {}", filename, e))
+            .collect::<Vec<String>>();
+        errors.sort();
+        errors
+    }
+
+    /// Compile every program in every library with a freshly read config.
+    /// Config problems are reported instead of compiling the program.
+    pub fn compile_all(&self, device: &wgpu::Device) -> Result<Vec<CompileReport>, String> {
+        let config = config::get_config(&self.libraries)?;
+        let mut reports = vec![];
+
+        for folder_name in config.get_folder_names() {
+            if let Some(error) = config.broken_folders.get(&folder_name) {
+                reports.push(CompileReport {
+                    folder: folder_name.clone(),
+                    program: None,
+                    errors: vec![error.clone()],
+                });
+            }
+
+            let folder_config = match config.folders.get(&folder_name) {
+                Some(c) => c,
+                None => continue,
+            };
+
+            let media_path = &self.libraries[folder_config.library].media_path;
+            for program_name in folder_config.get_program_names() {
+                let errors = match config.program_errors(&folder_name, &program_name) {
+                    Some(errors) => vec![errors],
+                    None => self.compile_program(
+                        device,
+                        &folder_config.programs[&program_name],
+                        &folder_name,
+                        media_path,
+                    ),
+                };
+
+                reports.push(CompileReport {
+                    folder: folder_name.clone(),
+                    program: Some(program_name),
+                    errors,
+                });
+            }
+        }
+
+        Ok(reports)
     }
 
     /// Fetch current GPU program.
@@ -518,12 +1385,7 @@ impl ProgramStore {
         }
 
         let program_names = &self.program_names.as_ref()?;
-        let name = &program_names[selected];
-
-        // first, clear the current program
-        if let Some(current_program) = &mut self.current_program {
-            current_program.clear();
-        }
+        let name = program_names[selected].clone();
 
         // next, update the current program and uniforms
         // it will be compiled in the next update()
@@ -534,7 +1396,7 @@ impl ProgramStore {
         let config = self.config.clone()?;
         let folder_config = config.folders.get(&folder_name).unwrap();
 
-        let program_config = match folder_config.programs.get(name) {
+        let program_config = match folder_config.programs.get(&name) {
             Some(c) => c,
             None => {
                 self.error = Some(format!("Missing program config '{}'", name));
@@ -548,6 +1410,7 @@ impl ProgramStore {
             encoder,
             program_config,
             folder_name,
+            name,
             num_samples,
             size,
         );
@@ -576,7 +1439,11 @@ impl ProgramStore {
         let folder_config = match config.folders.get(name) {
             Some(c) => c,
             None => {
-                self.error = Some(format!("Missing folder config '{}'", name));
+                // the running program keeps going behind the error
+                self.error = Some(match config.broken_folders.get(name) {
+                    Some(e) => format!("Folder '{}' failed to load\n{}", name, e),
+                    None => format!("Missing folder config '{}'", name),
+                });
                 return None;
             }
         };
@@ -602,7 +1469,10 @@ impl ProgramStore {
         };
 
         self.program_names = Some(program_names);
-        self.select_program(app, device, encoder, program_index, true, size, num_samples)
+        let selected =
+            self.select_program(app, device, encoder, program_index, true, size, num_samples);
+        self.prewarm_folder();
+        selected
     }
 
     /// Update GPU uniform buffers with current data.
@@ -618,6 +1488,14 @@ impl ProgramStore {
         }
     }
 
+    /// The number of vertices and instances the current program draws.
+    pub fn draw_counts(&self) -> (u32, u32) {
+        match &self.current_program {
+            Some(program) => program.draw_counts(),
+            None => (0, 0),
+        }
+    }
+
     /// Fetch the appropriate bind groups to set positions for the current program.
     /// Call in draw() right before rendering.
     pub fn get_bind_groups(&self) -> Option<Vec<&wgpu::BindGroup>> {
@@ -634,6 +1512,25 @@ impl ProgramStore {
         )
     }
 
+    /// The preprocessor defines of the current program.
+    pub fn get_defines(&self) -> Option<&HashMap<String, String>> {
+        if let Some(ref isf_pipeline) = self.isf_pipeline {
+            return Some(&isf_pipeline.defines);
+        }
+
+        let current_program = self.current_program.as_ref()?;
+        Some(&current_program.defines)
+    }
+
+    /// Change a preprocessor define of the current program, recompiling it.
+    pub fn set_define(&mut self, name: &str, value: String) {
+        if let Some(ref mut isf_pipeline) = self.isf_pipeline {
+            isf_pipeline.set_define(name, value);
+        } else if let Some(ref mut current_program) = self.current_program {
+            current_program.set_define(name, value);
+        }
+    }
+
     pub fn get_program_errors(&self) -> Option<program::ProgramErrors> {
         if let Some(ref isf_pipeline) = self.isf_pipeline {
             return isf_pipeline.get_program_errors();
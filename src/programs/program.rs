@@ -1,9 +1,14 @@
 use nannou::prelude::*;
 use rayon::prelude::*;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
+use crate::programs::cache;
+use crate::programs::compat;
 use crate::programs::config;
 use crate::programs::shaders;
+use crate::programs::uniforms::base::UniformBuffers;
+use crate::quad_2d;
 use crate::util;
 
 pub type ProgramErrors = HashMap<String, String>;
@@ -14,27 +19,64 @@ pub type ProgramErrors = HashMap<String, String>;
 #[derive(Debug)]
 pub struct Program {
     pub config: config::ProgramConfig,
+    pub defines: HashMap<String, String>,
     pub errors: ProgramErrors,
     pub pipeline: Option<wgpu::RenderPipeline>,
 
     frag_shader: shaders::Shader,
+    shaders_path: PathBuf,
     vert_shader: shaders::Shader,
 }
 
+/// A file in a folder, relative to the shaders directory unless it's absolute.
+fn folder_file(folder_name: &str, filename: &str) -> String {
+    if Path::new(filename).is_absolute() {
+        filename.to_string()
+    } else {
+        format!("{}/{}", folder_name, filename)
+    }
+}
+
+/// The vertex and fragment shader filenames of a program, relative to the shaders directory.
+pub fn shader_filenames(config: &config::ProgramConfig, folder_name: &str) -> (String, String) {
+    let frag_name = folder_file(folder_name, &config.pipeline.frag);
+    let mut vert_name = "default.vert".to_owned();
+    if let Some(name) = &config.pipeline.vert {
+        vert_name = folder_file(folder_name, name);
+    }
+    (vert_name, frag_name)
+}
+
+/// Parse a `topology` from the pipeline config.
+fn primitive_topology(name: &str) -> Result<wgpu::PrimitiveTopology, String> {
+    match name {
+        "points" => Ok(wgpu::PrimitiveTopology::PointList),
+        "lines" => Ok(wgpu::PrimitiveTopology::LineList),
+        "line_strip" => Ok(wgpu::PrimitiveTopology::LineStrip),
+        "triangles" => Ok(wgpu::PrimitiveTopology::TriangleList),
+        "triangle_strip" => Ok(wgpu::PrimitiveTopology::TriangleStrip),
+        _ => Err(format!(
+            "Invalid topology '{}', expected one of points, lines, line_strip, triangles, triangle_strip",
+            name
+        )),
+    }
+}
+
 impl Program {
-    pub fn new(config: config::ProgramConfig, folder_name: String) -> Self {
-        let frag_name = format!("{}/{}", folder_name, config.pipeline.frag);
-        let mut vert_name = "default.vert".to_owned();
-        if let Some(name) = &config.pipeline.vert {
-            vert_name = format!("{}/{}", folder_name, name);
-        }
+    pub fn new(config: config::ProgramConfig, folder_name: String, shaders_path: PathBuf) -> Self {
+        let (vert_name, frag_name) = shader_filenames(&config, &folder_name);
+
+        let defines = config.defines.clone().unwrap_or_default();
+        let frontend = compat::Frontend::from_config(&config);
 
         Self {
             config,
+            defines,
             errors: HashMap::new(),
-            frag_shader: shaders::Shader::new(frag_name),
+            frag_shader: shaders::Shader::new(frag_name, frontend),
             pipeline: None,
-            vert_shader: shaders::Shader::new(vert_name),
+            shaders_path,
+            vert_shader: shaders::Shader::new(vert_name, None),
         }
     }
 
@@ -47,15 +89,36 @@ impl Program {
         self.pipeline = None;
     }
 
+    /// Change the value of a preprocessor define.
+    /// The program will be recompiled on the next update.
+    pub fn set_define(&mut self, name: &str, value: String) {
+        self.defines.insert(name.to_string(), value);
+        self.clear();
+    }
+
+    /// The files this program is built from, including imports.
+    pub fn dependencies(&self) -> Vec<PathBuf> {
+        [&self.vert_shader, &self.frag_shader]
+            .iter()
+            .flat_map(|shader| {
+                let path = self
+                    .shaders_path
+                    .join(util::universal_path(shader.filename.clone()));
+                std::iter::once(path).chain(shader.imports.iter().cloned())
+            })
+            .collect()
+    }
+
     /// Compile the program with the latest shader code.
-    pub fn compile(&mut self, app: &App, device: &wgpu::Device) {
+    pub fn compile(&mut self, device: &wgpu::Device, spirv_cache: &cache::SpirvCache) {
         let mut shaders = [&mut self.vert_shader, &mut self.frag_shader];
-        let path = util::shaders_path(app);
+        let path = self.shaders_path.clone();
+        let defines = &self.defines;
 
         // compile shaders
         shaders.par_iter_mut().for_each(|shader| {
             let mut compiler = shaderc::Compiler::new().unwrap();
-            shader.compile(path.clone(), device, &mut compiler);
+            shader.compile(path.clone(), defines, device, &mut compiler, spirv_cache);
         });
 
         // collect errors
@@ -67,6 +130,73 @@ impl Program {
         });
     }
 
+    /// Check the resources WGSL shaders bind against the program's uniforms.
+    /// Mismatches are reported as errors on the shader.
+    pub fn validate_bindings(&mut self, buffers: &UniformBuffers) {
+        let uniforms = self.config.uniforms.clone().unwrap_or_default();
+
+        for shader in [&self.vert_shader, &self.frag_shader].iter() {
+            let mut messages = vec![];
+
+            for resource in shader.bindings.iter() {
+                let group = resource.group as usize;
+                let name = match uniforms.get(group) {
+                    Some(name) => name,
+                    None => {
+                        messages.push(format!(
+                            "group {} is out of range, the program has {} uniforms",
+                            group,
+                            uniforms.len()
+                        ));
+                        continue;
+                    }
+                };
+
+                let buffer = match buffers.get(name) {
+                    Some(buffer) => buffer,
+                    None => {
+                        messages.push(format!("group {}: unknown uniforms '{}'", group, name));
+                        continue;
+                    }
+                };
+
+                match buffer.bindings.get(resource.binding as usize) {
+                    Some(kind) if *kind == resource.kind => (),
+                    Some(kind) => messages.push(format!(
+                        "group {} binding {}: expected {:?} for '{}', found {:?}",
+                        group, resource.binding, kind, name, resource.kind
+                    )),
+                    None => messages.push(format!(
+                        "group {} binding {}: '{}' only has {} bindings",
+                        group,
+                        resource.binding,
+                        name,
+                        buffer.bindings.len()
+                    )),
+                }
+            }
+
+            if !messages.is_empty() {
+                self.errors
+                    .insert(shader.filename.to_string(), messages.join("\n"));
+            }
+        }
+    }
+
+    /// Whether the program generates its own vertices rather than drawing the fullscreen quad.
+    pub fn is_vertex_mode(&self) -> bool {
+        self.config.pipeline.vertex_count.is_some()
+    }
+
+    /// The number of vertices and instances to draw.
+    pub fn draw_counts(&self) -> (u32, u32) {
+        let pipeline = &self.config.pipeline;
+        match pipeline.vertex_count {
+            Some(count) => (count, pipeline.instance_count.unwrap_or(1)),
+            None => (quad_2d::VERTICES.len() as u32, 1),
+        }
+    }
+
     /// Create the render pipeline
     pub fn create_render_pipeline(
         &mut self,
@@ -79,16 +209,53 @@ impl Program {
             return;
         }
 
+        let mut topology = None;
+        if self.is_vertex_mode() {
+            if self.config.pipeline.vert.is_none() {
+                self.errors.insert(
+                    "pipeline".to_string(),
+                    "vertex_count requires a vert shader".to_string(),
+                );
+                self.pipeline = None;
+                return;
+            }
+
+            let name = self
+                .config
+                .pipeline
+                .topology
+                .as_deref()
+                .unwrap_or("triangles");
+            match primitive_topology(name) {
+                Ok(t) => topology = Some(t),
+                Err(e) => {
+                    self.errors.insert("pipeline".to_string(), e);
+                    self.pipeline = None;
+                    return;
+                }
+            }
+        }
+
         if let Some(vert_module) = &self.vert_shader.module {
             if let Some(frag_module) = &self.frag_shader.module {
                 println!("creating pipeline");
-                self.pipeline = Some(util::create_pipeline(
-                    device,
-                    layout_desc,
-                    vert_module,
-                    frag_module,
-                    num_samples,
-                ));
+                self.pipeline = Some(match topology {
+                    Some(topology) => util::create_vertex_pipeline(
+                        device,
+                        layout_desc,
+                        vert_module,
+                        frag_module,
+                        topology,
+                        num_samples,
+                    ),
+                    None => util::create_pipeline(
+                        device,
+                        layout_desc,
+                        vert_module,
+                        frag_module,
+                        num_samples,
+                    ),
+                });
             }
         }
     }
@@ -1,10 +1,12 @@
 use nannou::prelude::*;
 use regex::Regex;
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use std::sync::mpsc::channel;
-use std::thread;
+use std::path::{Path, PathBuf};
 
+use crate::programs::cache;
+use crate::programs::compat;
+use crate::programs::uniforms::base::BindingKind;
 use crate::util;
 
 /// Stores data that represents a single shader file
@@ -13,15 +15,215 @@ use crate::util;
 pub struct Shader {
     pub error: Option<String>,
     pub filename: String,
+    pub frontend: Option<compat::Frontend>,
+    pub imports: Vec<PathBuf>,
     pub module: Option<wgpu::ShaderModule>,
+    /// The resources a WGSL shader declares, used to validate it against the program's uniforms.
+    pub bindings: Vec<ResourceBinding>,
+}
+
+/// A resource a shader expects at a bind group and binding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceBinding {
+    pub group: u32,
+    pub binding: u32,
+    pub kind: BindingKind,
+}
+
+pub fn is_wgsl(filename: &str) -> bool {
+    filename.ends_with(".wgsl")
+}
+
+/// Parse and validate WGSL source, returning the resources it binds.
+/// Errors are reported with the line and column they occurred at.
+pub fn parse_wgsl(filename: &str, source: &str) -> Result<Vec<ResourceBinding>, String> {
+    let module = match naga::front::wgsl::parse_str(source) {
+        Ok(m) => m,
+        Err(e) => {
+            return Err(format!("{}:{}:{}: {}", filename, e.line, e.pos, e.error));
+        }
+    };
+
+    if let Err(e) = naga::proc::Validator::new().validate(&module) {
+        return Err(format!("{}: {}", filename, e));
+    }
+
+    let bindings = module
+        .global_variables
+        .iter()
+        .filter_map(|(_, var)| match var.binding {
+            Some(naga::Binding::Resource { group, binding }) => {
+                let kind = match module.types[var.ty].inner {
+                    naga::TypeInner::Sampler { .. } => BindingKind::Sampler,
+                    naga::TypeInner::Image { .. } => BindingKind::Texture,
+                    _ => BindingKind::Buffer,
+                };
+                Some(ResourceBinding {
+                    group,
+                    binding,
+                    kind,
+                })
+            }
+            _ => None,
+        })
+        .collect();
+
+    Ok(bindings)
+}
+
+fn shader_kind(filename: &str) -> shaderc::ShaderKind {
+    let split = filename.split('.').collect::<Vec<&str>>();
+    let ext = split[1];
+    let mut kind = shaderc::ShaderKind::Fragment;
+    if ext == "vert" {
+        kind = shaderc::ShaderKind::Vertex;
+    }
+    kind
+}
+
+/// The path of a file referenced with `//@import`.
+fn import_path(shaders_path: &Path, import: &str) -> PathBuf {
+    shaders_path.join(util::universal_path(format!("{}.glsl", import)))
+}
+
+fn import_regex() -> Regex {
+    // ([^\r]*) deals with \r on windows
+    Regex::new(r"//@import ([^\r\n]*)").unwrap()
+}
+
+/// The files referenced by the `//@import` directives in a shader's source.
+pub fn imports(shaders_path: &Path, source: &str) -> Vec<PathBuf> {
+    import_regex()
+        .captures_iter(source)
+        .map(|captures| import_path(shaders_path, &captures[1]))
+        .collect()
+}
+
+/// Replace each `//@import` directive with the contents of the imported file.
+/// Imports are resolved relative to the shaders directory.
+pub fn expand_imports(shaders_path: &Path, source: &str, filename: &str) -> Result<String, String> {
+    let mut error = None;
+
+    let complete = import_regex()
+        .replace_all(source, |captures: &regex::Captures| {
+            let path = import_path(shaders_path, &captures[1]);
+            match fs::read_to_string(&path) {
+                Ok(import_src) => format!("\n{}", import_src),
+                Err(_) => {
+                    error = Some(format!(
+                        "Error importing {} from {}",
+                        path.to_string_lossy(),
+                        filename
+                    ));
+                    "".to_string()
+                }
+            }
+        })
+        .to_string();
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(complete),
+    }
+}
+
+/// Read a shader file and expand its imports.
+fn read_source(shaders_path: &Path, filename: &str) -> Result<String, String> {
+    let src_string = match fs::read_to_string(util::universal_path(filename.to_string())) {
+        Ok(s) => s,
+        Err(_) => {
+            return Err(format!("Error reading shader: {}", filename));
+        }
+    };
+
+    expand_imports(shaders_path, &src_string, filename)
+}
+
+/// Insert a `#define` for each entry directly after the version directive.
+/// Defines are sorted by name so the same map always produces the same source.
+/// Returns the new source and the number of lines that were added.
+pub fn inject_defines(source: &str, defines: &HashMap<String, String>) -> (String, usize) {
+    if defines.is_empty() {
+        return (source.to_string(), 0);
+    }
+
+    let mut names = defines.keys().collect::<Vec<&String>>();
+    names.sort();
+
+    let mut define_str = String::new();
+    for name in names.iter() {
+        define_str.push_str(&format!("#define {} {}\n", name, defines[*name]));
+    }
+
+    // the version directive must stay at the top
+    let insert_at = match source.find("#version ") {
+        Some(start) => match source[start..].find('\n') {
+            Some(end) => start + end + 1,
+            None => source.len(),
+        },
+        None => 0,
+    };
+
+    let mut output = source[..insert_at].to_string();
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output.push_str(&define_str);
+    output.push_str(&source[insert_at..]);
+
+    (output, names.len())
+}
+
+/// Compile a shader file to SPIR-V.
+/// The result is looked up in and added to the SPIR-V cache.
+pub fn compile_spirv(
+    shaders_path: &Path,
+    filename: &str,
+    frontend: Option<&compat::Frontend>,
+    defines: &HashMap<String, String>,
+    compiler: &mut shaderc::Compiler,
+    spirv_cache: &cache::SpirvCache,
+) -> Result<Vec<u8>, String> {
+    let kind = shader_kind(filename);
+
+    let filename = shaders_path
+        .join(filename)
+        .into_os_string()
+        .into_string()
+        .unwrap();
+    println!("reading: {}", filename);
+    let mut source = read_source(shaders_path, filename.as_str())?;
+    if let Some(frontend) = frontend {
+        source = frontend.translate(&source);
+    }
+    let (complete_src, _) = inject_defines(&source, defines);
+
+    let kind_name = format!("{:?}", kind);
+    if let Some(bytes) = cache::get_spirv(spirv_cache, &kind_name, &complete_src) {
+        return Ok(bytes);
+    }
+
+    // compile shader
+    match compiler.compile_into_spirv(complete_src.as_str(), kind, filename.as_str(), "main", None)
+    {
+        Ok(program) => {
+            let bytes = program.as_binary_u8();
+            cache::insert_spirv(spirv_cache, &kind_name, &complete_src, bytes);
+            Ok(bytes.to_vec())
+        }
+        Err(e) => Err(e.to_string()),
+    }
 }
 
 impl Shader {
-    pub fn new(filename: String) -> Self {
+    pub fn new(filename: String, frontend: Option<compat::Frontend>) -> Self {
         Self {
             error: None,
             filename,
+            frontend,
+            imports: vec![],
             module: None,
+            bindings: vec![],
         }
     }
 
@@ -29,100 +231,64 @@ impl Shader {
     pub fn compile(
         &mut self,
         shaders_path: PathBuf,
+        defines: &HashMap<String, String>,
         device: &wgpu::Device,
         compiler: &mut shaderc::Compiler,
+        spirv_cache: &cache::SpirvCache,
     ) {
-        let split = self.filename.split('.').collect::<Vec<&str>>();
-        let ext = split[1];
-        let mut kind = shaderc::ShaderKind::Fragment;
-        if ext == "vert" {
-            kind = shaderc::ShaderKind::Vertex;
+        let path = shaders_path.join(util::universal_path(self.filename.clone()));
+        if is_wgsl(&self.filename) {
+            self.compile_wgsl(&path, device);
+            return;
         }
 
-        let filename = shaders_path
-            .join(self.filename.clone())
-            .into_os_string()
-            .into_string()
-            .unwrap();
-        println!("reading: {}", filename);
-        let src_string = match fs::read_to_string(util::universal_path(filename.clone())) {
-            Ok(s) => s,
-            Err(_) => {
-                self.error = Some(format!("Error reading shader: {}", filename));
-                return;
-            }
-        };
+        // keep track of imports even if compilation fails so fixing them triggers a recompile
+        if let Ok(source) = fs::read_to_string(&path) {
+            self.imports = imports(&shaders_path, &source);
+        }
 
-        let (tx, rx) = channel();
-
-        // load shader dependencies ([^\r]*) deals with \r on windows
-        let re = Regex::new(r"//@import ([^\r\n]*)").unwrap();
-        let flnm = filename.clone();
-        thread::spawn(move || {
-            let complete = re
-                .replace_all(src_string.as_str(), |captures: &regex::Captures| {
-                    let import = &captures[1];
-                    let import_path = match shaders_path
-                        .clone()
-                        .join(format!("{}.glsl", import))
-                        .into_os_string()
-                        .into_string()
-                    {
-                        Ok(s) => s,
-                        Err(_) => {
-                            tx.send(Err(format!(
-                                "Error building path for {} from {}",
-                                import, flnm
-                            )))
-                            .unwrap();
-                            return "".to_string();
-                        }
-                    };
-
-                    let mut import_src = "\n".to_owned();
-                    let import_src_string = match fs::read_to_string(import_path.clone()) {
-                        Ok(s) => s,
-                        Err(_) => {
-                            tx.send(Err(format!(
-                                "Error importing {} from {}",
-                                import_path, flnm
-                            )))
-                            .unwrap();
-                            return "".to_string();
-                        }
-                    };
-                    import_src.push_str(import_src_string.as_str());
-                    import_src
-                })
-                .to_string();
-            tx.send(Ok(complete)).unwrap();
-        });
+        match compile_spirv(
+            &shaders_path,
+            &self.filename,
+            self.frontend.as_ref(),
+            defines,
+            compiler,
+            spirv_cache,
+        ) {
+            Ok(bytes) => {
+                self.module = Some(wgpu::shader_from_spirv_bytes(device, &bytes));
+                self.error = None;
+            }
+            Err(e) => {
+                self.error = Some(e);
+            }
+        }
+    }
 
-        let complete_src = match rx.recv().unwrap() {
+    /// WGSL goes through wgpu's own front end instead of shaderc.
+    /// Imports and defines are not supported.
+    fn compile_wgsl(&mut self, path: &Path, device: &wgpu::Device) {
+        let source = match fs::read_to_string(path) {
             Ok(s) => s,
-            Err(err) => {
-                self.error = Some(err);
+            Err(_) => {
+                self.error = Some(format!("Error reading shader: {}", self.filename));
                 return;
             }
         };
 
-        // compile shader
-        match compiler.compile_into_spirv(
-            complete_src.as_str(),
-            kind,
-            filename.as_str(),
-            "main",
-            None,
-        ) {
-            Ok(program) => {
-                self.module = Some(wgpu::shader_from_spirv_bytes(
-                    device,
-                    &program.as_binary_u8(),
-                ));
+        match parse_wgsl(&self.filename, &source) {
+            Ok(bindings) => {
+                self.module = Some(device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+                    label: Some(&self.filename),
+                    source: wgpu::ShaderSource::Wgsl(source.into()),
+                    flags: wgpu::ShaderFlags::VALIDATION,
+                }));
+                self.bindings = bindings;
                 self.error = None;
             }
             Err(e) => {
-                self.error = Some(e.to_string());
+                self.bindings = vec![];
+                self.error = Some(e);
             }
         }
     }
@@ -16,6 +16,19 @@ pub trait Bufferable<T = Data>: Sized {
     }
 }
 
+/// Uniforms are available to vertex shaders too, for programs that generate their own vertices.
+const VISIBILITY: wgpu::ShaderStage = wgpu::ShaderStage::from_bits_truncate(
+    wgpu::ShaderStage::VERTEX.bits() | wgpu::ShaderStage::FRAGMENT.bits(),
+);
+
+/// The type of resource at each binding of a uniform buffer's bind group.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BindingKind {
+    Sampler,
+    Texture,
+    Buffer,
+}
+
 /// Stores a uniform buffer along with the relevant bind groups.
 /// Maintains the uniform buffer.
 /// Since this application requires many uniform buffers but nearly no
@@ -25,6 +38,7 @@ pub trait Bufferable<T = Data>: Sized {
 pub struct UniformBuffer {
     pub bind_group: wgpu::BindGroup,
     pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bindings: Vec<BindingKind>,
     pub buffer: Option<wgpu::Buffer>,
 }
 
@@ -38,25 +52,29 @@ impl UniformBuffer {
 
         let mut layout_builder = wgpu::BindGroupLayoutBuilder::new();
         let mut texture_views = vec![];
+        let mut bindings = vec![];
 
         if !textures.is_empty() {
-            layout_builder = layout_builder.sampler(wgpu::ShaderStage::FRAGMENT, false);
+            layout_builder = layout_builder.sampler(VISIBILITY, false);
+            bindings.push(BindingKind::Sampler);
 
             for texture in textures.iter() {
                 let texture_view = texture.view().build();
                 layout_builder = layout_builder.texture(
-                    wgpu::ShaderStage::FRAGMENT,
+                    VISIBILITY,
                     false,
                     wgpu::TextureViewDimension::D2,
                     texture_view.sample_type(),
                 );
                 texture_views.push(texture_view);
+                bindings.push(BindingKind::Texture);
             }
         }
 
         let mut buffer = None;
         if !data.is_empty() {
-            layout_builder = layout_builder.uniform_buffer(wgpu::ShaderStage::FRAGMENT, false);
+            layout_builder = layout_builder.uniform_buffer(VISIBILITY, false);
+            bindings.push(BindingKind::Buffer);
             let usage = wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST;
             let buff = device.create_buffer_init(&BufferInitDescriptor {
                 label: None,
@@ -87,6 +105,7 @@ impl UniformBuffer {
         Self {
             bind_group,
             bind_group_layout,
+            bindings,
             buffer,
         }
     }
@@ -1,9 +1,9 @@
 use nannou::image;
 use nannou::image::GenericImageView;
 use nannou::prelude::*;
+use std::path::Path;
 use tinyfiledialogs::open_file_dialog;
 
-use crate::app;
 use crate::programs::config;
 use crate::programs::uniforms::base::Bufferable;
 use crate::util;
@@ -23,6 +23,9 @@ pub struct ImageUniforms {
     pub image2_name: Option<String>,
     pub image2_texture: wgpu::Texture,
     pub updated: bool,
+
+    image1_path: Option<String>,
+    image2_path: Option<String>,
 }
 
 impl Bufferable<Data> for ImageUniforms {
@@ -47,14 +50,23 @@ impl ImageUniforms {
             },
             error: None,
             image1_name: None,
+            image1_path: None,
             image1_texture,
             image2_name: None,
+            image2_path: None,
             image2_texture,
             updated: false,
         }
     }
 
     pub fn load_image(&mut self, app: &App, image_id: i32, filepath: String) {
+        // remember the path even if loading fails so the image is picked up once it exists
+        match image_id {
+            1 => self.image1_path = Some(filepath.clone()),
+            2 => self.image2_path = Some(filepath.clone()),
+            _ => return,
+        };
+
         let img = match image::open(&filepath) {
             Ok(img) => img,
             Err(e) => {
@@ -86,16 +98,18 @@ impl ImageUniforms {
         self.updated = true;
     }
 
-    pub fn configure(&mut self, app: &App, settings: &Option<config::ProgramSettings>) {
+    pub fn configure(
+        &mut self,
+        app: &App,
+        media_path: &Path,
+        settings: &Option<config::ProgramSettings>,
+    ) {
         if let Some(cnfg) = settings {
-            let project_path = app.project_path().expect("failed to locate `project_path`");
-
             if let Some(img1) = &cnfg.image1 {
                 self.load_image(
                     app,
                     1,
-                    project_path
-                        .join(app::MEDIA_DIR)
+                    media_path
                         .join(img1)
                         .into_os_string()
                         .into_string()
@@ -107,8 +121,7 @@ impl ImageUniforms {
                 self.load_image(
                     app,
                     2,
-                    project_path
-                        .join(app::MEDIA_DIR)
+                    media_path
                         .join(img2)
                         .into_os_string()
                         .into_string()
@@ -118,6 +131,52 @@ impl ImageUniforms {
         }
     }
 
+    fn clear_image(&mut self, device: &wgpu::Device, image_id: i32) {
+        let texture = util::create_texture(device, [1, 1], wgpu::TextureFormat::Rgba16Float);
+
+        match image_id {
+            1 => {
+                self.image1_name = None;
+                self.image1_texture = texture;
+                self.data.image1_size = pt2(0.0, 0.0);
+            }
+            2 => {
+                self.image2_name = None;
+                self.image2_texture = texture;
+                self.data.image2_size = pt2(0.0, 0.0);
+            }
+            _ => return,
+        };
+
+        self.updated = true;
+    }
+
+    /// Reload an image whose file was changed or created.
+    pub fn media_changed(&mut self, app: &App, filepath: &str) {
+        if self.image1_path.as_deref() == Some(filepath) {
+            self.error = None;
+            self.load_image(app, 1, filepath.to_string());
+        }
+
+        if self.image2_path.as_deref() == Some(filepath) {
+            self.error = None;
+            self.load_image(app, 2, filepath.to_string());
+        }
+    }
+
+    /// Unload an image whose file was removed.
+    pub fn media_removed(&mut self, device: &wgpu::Device, filepath: &str) {
+        if self.image1_path.as_deref() == Some(filepath) {
+            self.error = Some(format!("Image removed: {}", filepath));
+            self.clear_image(device, 1);
+        }
+
+        if self.image2_path.as_deref() == Some(filepath) {
+            self.error = Some(format!("Image removed: {}", filepath));
+            self.clear_image(device, 2);
+        }
+    }
+
     pub fn select_image(&mut self, app: &App, image_id: i32) {
         let filepath = match open_file_dialog("Load Image", "~", Some((&["*.jpg", "*.png"], ""))) {
             Some(filepath) => filepath,
@@ -1,5 +1,6 @@
 use nannou::prelude::*;
 use std::collections::HashMap;
+use std::path::Path;
 
 use crate::programs::config;
 
@@ -14,11 +15,13 @@ pub mod geometry;
 pub mod image;
 pub mod multipass;
 pub mod noise;
+pub mod points;
+pub mod shadertoy;
 pub mod video;
 pub mod video_capture;
 pub mod webcam;
 
-use base::{UniformBuffers, UniformBuffersMethods};
+use base::{Bufferable, UniformBuffers, UniformBuffersMethods};
 
 /// Defines a program's subscriptions to uniform data.
 /// This determines which data should be fetched / updated.
@@ -33,10 +36,29 @@ pub struct UniformSubscriptions {
     pub image: bool,
     pub noise: bool,
     pub multipass: bool,
+    pub points: bool,
+    pub shadertoy: bool,
     pub video: bool,
     pub webcam: bool,
 }
 
+/// Every uniform group a program can subscribe to.
+pub const UNIFORM_NAMES: [&str; 13] = [
+    "audio",
+    "audio_fft",
+    "camera",
+    "color",
+    "general",
+    "geometry",
+    "image",
+    "multipass",
+    "noise",
+    "points",
+    "shadertoy",
+    "video",
+    "webcam",
+];
+
 /// Build a subscriptions struct from a list of uniform names
 pub fn get_subscriptions(names: &[String]) -> UniformSubscriptions {
     let mut subscriptions = UniformSubscriptions {
@@ -49,6 +71,8 @@ pub fn get_subscriptions(names: &[String]) -> UniformSubscriptions {
         image: false,
         noise: false,
         multipass: false,
+        points: false,
+        shadertoy: false,
         video: false,
         webcam: false,
     };
@@ -63,6 +87,8 @@ pub fn get_subscriptions(names: &[String]) -> UniformSubscriptions {
         "image" => subscriptions.image = true,
         "noise" => subscriptions.noise = true,
         "multipass" => subscriptions.multipass = true,
+        "points" => subscriptions.points = true,
+        "shadertoy" => subscriptions.shadertoy = true,
         "video" => subscriptions.video = true,
         "webcam" => subscriptions.webcam = true,
         _ => (),
@@ -85,6 +111,8 @@ pub struct BufferStore {
     pub image_uniforms: image::ImageUniforms,
     pub noise_uniforms: noise::NoiseUniforms,
     pub multipass_uniforms: multipass::MultipassUniforms,
+    pub point_uniforms: points::PointUniforms,
+    pub shadertoy_uniforms: shadertoy::ShadertoyUniforms,
     pub video_uniforms: video::VideoUniforms,
     pub webcam_uniforms: webcam::WebcamUniforms,
 }
@@ -124,6 +152,12 @@ impl BufferStore {
         let noise_uniforms = noise::NoiseUniforms::new();
         buffers.add(device, "noise", &noise_uniforms);
 
+        let point_uniforms = points::PointUniforms::new();
+        buffers.add(device, "points", &point_uniforms);
+
+        let shadertoy_uniforms = shadertoy::ShadertoyUniforms::new(device);
+        buffers.add(device, "shadertoy", &shadertoy_uniforms);
+
         let video_uniforms = video::VideoUniforms::new();
         buffers.add(device, "video", &video_uniforms);
 
@@ -142,11 +176,49 @@ impl BufferStore {
             image_uniforms,
             multipass_uniforms,
             noise_uniforms,
+            point_uniforms,
+            shadertoy_uniforms,
             video_uniforms,
             webcam_uniforms,
         }
     }
 
+    /// The texture a Shadertoy channel reads from, if the channel is bound.
+    fn channel_texture(&self, channel: &str) -> Option<&wgpu::Texture> {
+        match channel {
+            "image1" => Some(&self.image_uniforms.image1_texture),
+            "image2" => Some(&self.image_uniforms.image2_texture),
+            "video" => self.video_uniforms.textures().pop(),
+            "webcam" => self.webcam_uniforms.textures().pop(),
+            "audio" => self.audio_uniforms.textures().pop(),
+            "audio_fft" => self.audio_fft_uniforms.textures().pop(),
+            _ => {
+                let index = channel.strip_prefix("pass")?.parse::<usize>().ok()?;
+                self.multipass_uniforms
+                    .textures()
+                    .get(index.checked_sub(1)?)
+                    .copied()
+            }
+        }
+    }
+
+    fn channel_sizes(&self) -> [[f32; 2]; shadertoy::NUM_CHANNELS] {
+        let mut sizes = [[0.0; 2]; shadertoy::NUM_CHANNELS];
+        for (i, channel) in self
+            .shadertoy_uniforms
+            .channels
+            .iter()
+            .take(shadertoy::NUM_CHANNELS)
+            .enumerate()
+        {
+            if let Some(texture) = self.channel_texture(channel) {
+                let [w, h] = texture.size();
+                sizes[i] = [w as f32, h as f32];
+            }
+        }
+        sizes
+    }
+
     pub fn start_audio_session(&mut self, subscriptions: &UniformSubscriptions) {
         if !(subscriptions.audio || subscriptions.audio_fft) {
             return;
@@ -178,6 +250,7 @@ impl BufferStore {
         app: &App,
         device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
+        media_path: &Path,
         subscriptions: &UniformSubscriptions,
         settings: &Option<config::ProgramSettings>,
         size: Point2,
@@ -191,7 +264,7 @@ impl BufferStore {
 
         self.color_uniforms.configure(settings);
 
-        self.image_uniforms.configure(app, settings);
+        self.image_uniforms.configure(app, media_path, settings);
 
         self.multipass_uniforms
             .configure(settings, device, encoder, size, num_samples);
@@ -200,9 +273,13 @@ impl BufferStore {
 
         self.noise_uniforms.configure(settings);
 
+        self.point_uniforms.configure(settings, size);
+
+        self.shadertoy_uniforms.configure(settings);
+
         self.video_uniforms.end_session();
         if subscriptions.video {
-            self.video_uniforms.configure(app, device, settings);
+            self.video_uniforms.configure(device, media_path, settings);
             if self.video_uniforms.updated {
                 self.buffers.add(device, "video", &self.video_uniforms);
             }
@@ -240,7 +317,7 @@ impl BufferStore {
             self.audio_fft_uniforms.update();
         }
 
-        if subscriptions.general {
+        if subscriptions.general || subscriptions.shadertoy {
             self.general_uniforms.update();
         }
 
@@ -267,6 +344,12 @@ impl BufferStore {
         if subscriptions.webcam {
             self.webcam_uniforms.update();
         }
+
+        if subscriptions.shadertoy {
+            let channel_sizes = self.channel_sizes();
+            self.shadertoy_uniforms
+                .update(&self.general_uniforms.data, channel_sizes);
+        }
     }
 
     /// Update GPU uniform buffers with current data.
@@ -315,6 +398,16 @@ impl BufferStore {
                 .update(device, encoder, "noise", &self.noise_uniforms);
         }
 
+        if subscriptions.points {
+            self.buffers
+                .update(device, encoder, "points", &self.point_uniforms);
+        }
+
+        if subscriptions.shadertoy {
+            self.buffers
+                .update(device, encoder, "shadertoy", &self.shadertoy_uniforms);
+        }
+
         if subscriptions.video {
             self.video_uniforms.update_texture(device, encoder);
             self.buffers
@@ -0,0 +1,65 @@
+use nannou::prelude::*;
+
+use crate::programs::config;
+use crate::programs::uniforms::base::Bufferable;
+
+/// The most points a program can name.
+pub const MAX_POINTS: usize = 8;
+
+/// Points are consecutive `vec2`s, so a shader declares them as fields in the order of the
+/// config's `points` list.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Data {
+    pub points: [[f32; 2]; MAX_POINTS],
+}
+
+/// Named 2D values that are set by dragging on the canvas, in pixels from the bottom left.
+#[derive(Debug, Clone)]
+pub struct PointUniforms {
+    pub data: Data,
+    pub names: Vec<String>,
+}
+
+impl Bufferable<Data> for PointUniforms {
+    fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(&self.data) }
+    }
+}
+
+impl PointUniforms {
+    pub fn new() -> Self {
+        Self {
+            data: Data {
+                points: [[0.0; 2]; MAX_POINTS],
+            },
+            names: vec![],
+        }
+    }
+
+    /// Name the program's points and start them in the middle of the canvas.
+    pub fn configure(&mut self, settings: &Option<config::ProgramSettings>, size: Point2) {
+        let mut names = vec![];
+
+        if let Some(cnfg) = settings {
+            if let Some(points) = &cnfg.points {
+                names = points.clone();
+            }
+        }
+
+        names.truncate(MAX_POINTS);
+        self.names = names;
+        self.data.points = [[size[0] / 2.0, size[1] / 2.0]; MAX_POINTS];
+    }
+
+    pub fn get(&self, index: usize) -> Option<Point2> {
+        let [x, y] = self.data.points.get(index)?;
+        Some(pt2(*x, *y))
+    }
+
+    pub fn set(&mut self, index: usize, point: Point2) {
+        if let Some(p) = self.data.points.get_mut(index) {
+            *p = [point.x, point.y];
+        }
+    }
+}
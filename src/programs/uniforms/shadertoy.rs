@@ -0,0 +1,167 @@
+use nannou::prelude::*;
+
+use crate::programs::config;
+use crate::programs::uniforms::base::Bufferable;
+use crate::programs::uniforms::general;
+use crate::util;
+
+/// The number of texture channels a Shadertoy program can read from.
+pub const NUM_CHANNELS: usize = 4;
+
+/// Laid out to match the std140 `ShadertoyUniforms` block declared by the Shadertoy wrapper.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Data {
+    pub channel_resolution: [[f32; 4]; NUM_CHANNELS],
+    pub date: [f32; 4],
+    pub mouse: [f32; 4],
+    pub resolution: [f32; 3],
+    pub time: f32,
+    pub time_delta: f32,
+    pub frame: i32,
+    _padding: [i32; 2],
+}
+
+impl Data {
+    fn new() -> Self {
+        Self {
+            channel_resolution: [[0.0; 4]; NUM_CHANNELS],
+            date: [0.0; 4],
+            mouse: [0.0; 4],
+            resolution: [0.0, 0.0, 1.0],
+            time: 0.0,
+            time_delta: 0.0,
+            frame: -1,
+            _padding: [0; 2],
+        }
+    }
+
+    /// Start counting frames again, the next update is frame 0 like on Shadertoy.
+    fn reset(&mut self) {
+        self.frame = -1;
+        self.time = 0.0;
+    }
+
+    /// Advance to the next frame from the general uniforms.
+    fn advance(&mut self, general: &general::Data, channel_sizes: [[f32; 2]; NUM_CHANNELS]) {
+        self.time_delta = (general.time - self.time).max(0.0);
+        self.time = general.time;
+        self.frame += 1;
+        self.resolution = [general.resolution.x, general.resolution.y, 1.0];
+
+        // Shadertoy's year, 0 based month, day, seconds
+        let [year, month, day, seconds] = util::current_date();
+        self.date = [year, month - 1.0, day, seconds];
+
+        for (i, size) in channel_sizes.iter().enumerate() {
+            self.channel_resolution[i] = [size[0], size[1], 1.0, 0.0];
+        }
+    }
+}
+
+/// Shadertoy's built in inputs, derived from the general uniforms every frame.
+/// Also holds an empty texture that unbound channels sample from.
+pub struct ShadertoyUniforms {
+    pub channels: Vec<String>,
+    pub data: Data,
+
+    click: Vector2<f32>,
+    empty_texture: wgpu::Texture,
+    mouse_was_down: bool,
+}
+
+impl Bufferable<Data> for ShadertoyUniforms {
+    fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(&self.data) }
+    }
+
+    fn textures(&self) -> Vec<&wgpu::Texture> {
+        vec![&self.empty_texture]
+    }
+}
+
+impl ShadertoyUniforms {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let empty_texture = util::create_texture(device, [1, 1], wgpu::TextureFormat::Rgba16Float);
+
+        Self {
+            channels: vec![],
+            data: Data::new(),
+            click: pt2(0.0, 0.0),
+            empty_texture,
+            mouse_was_down: false,
+        }
+    }
+
+    pub fn configure(&mut self, settings: &Option<config::ProgramSettings>) {
+        self.channels = settings
+            .as_ref()
+            .and_then(|s| s.channels.clone())
+            .unwrap_or_default();
+        self.data.reset();
+    }
+
+    /// Update the Shadertoy inputs from the general uniforms.
+    /// `channel_sizes` holds the size of the texture bound to each channel.
+    pub fn update(&mut self, general: &general::Data, channel_sizes: [[f32; 2]; NUM_CHANNELS]) {
+        self.data.advance(general, channel_sizes);
+
+        let width = general.resolution.x;
+        let height = general.resolution.y;
+
+        // pixel coordinates with the origin in the bottom left
+        let position = pt2(
+            general.mouse.x + width / 2.0,
+            general.mouse.y + height / 2.0,
+        );
+        let mouse_down = general.mouse_down != 0;
+        let clicked = mouse_down && !self.mouse_was_down;
+        self.mouse_was_down = mouse_down;
+
+        if clicked {
+            self.click = position;
+        }
+
+        // xy follows the mouse while pressed, zw is the last click and is negated when released
+        if mouse_down {
+            self.data.mouse[0] = position.x;
+            self.data.mouse[1] = position.y;
+            self.data.mouse[2] = self.click.x;
+            self.data.mouse[3] = if clicked { self.click.y } else { -self.click.y };
+        } else {
+            self.data.mouse[2] = -self.click.x.abs();
+            self.data.mouse[3] = -self.click.y.abs();
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_frame_counter() {
+    let mut data = Data::new();
+    let mut general = general::Data {
+        mouse: pt2(0.0, 0.0),
+        resolution: vec2(640.0, 480.0),
+        time: 0.5,
+        mouse_down: 0,
+    };
+    let sizes = [[0.0; 2]; NUM_CHANNELS];
+
+    // the first uploaded frame is 0
+    data.advance(&general, sizes);
+    assert_eq!(data.frame, 0);
+    assert_eq!(data.time, 0.5);
+    assert_eq!(data.resolution, [640.0, 480.0, 1.0]);
+
+    general.time = 0.75;
+    data.advance(&general, sizes);
+    assert_eq!(data.frame, 1);
+    assert_eq!(data.time_delta, 0.25);
+
+    // switching programs starts over
+    data.reset();
+    general.time = 0.0;
+    data.advance(&general, sizes);
+    assert_eq!(data.frame, 0);
+    assert_eq!(data.time_delta, 0.0);
+}
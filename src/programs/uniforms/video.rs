@@ -1,7 +1,7 @@
 use nannou::prelude::*;
+use std::path::Path;
 use tinyfiledialogs::open_file_dialog;
 
-use crate::app;
 use crate::programs::config;
 use crate::programs::uniforms::base::Bufferable;
 use crate::programs::uniforms::video_capture::VideoCapture;
@@ -62,16 +62,13 @@ impl VideoUniforms {
 
     pub fn configure(
         &mut self,
-        app: &App,
         device: &wgpu::Device,
+        media_path: &Path,
         settings: &Option<config::ProgramSettings>,
     ) {
         if let Some(cnfg) = settings {
-            let project_path = app.project_path().expect("failed to locate `project_path`");
-
             if let Some(video) = &cnfg.video {
-                let video_path = project_path
-                    .join(app::MEDIA_DIR)
+                let video_path = media_path
                     .join(video)
                     .into_os_string()
                     .into_string()
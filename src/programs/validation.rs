@@ -0,0 +1,618 @@
+use serde_json::{json, Value};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::programs::config;
+use crate::programs::uniforms;
+
+/// How bad a diagnostic is, only errors keep a program from loading.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in an `index.json` file, with the position it was found at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+    /// The folder and program the problem belongs to, if any.
+    pub folder: Option<String>,
+    pub program: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.path.to_string_lossy(),
+            self.line,
+            self.column,
+            self.severity,
+            self.message
+        )
+    }
+}
+
+impl Diagnostic {
+    /// A diagnostic for a file that couldn't be parsed.
+    pub fn from_json_error(path: &Path, error: &serde_json::Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            line: error.line(),
+            column: error.column(),
+            severity: Severity::Error,
+            message: error.to_string(),
+            folder: None,
+            program: None,
+        }
+    }
+}
+
+/// The location of an object key, or of a string in an array, in a JSON file.
+/// `path` holds the keys and array indices leading to it.
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    path: Vec<String>,
+    offset: usize,
+}
+
+enum Container {
+    Object(Option<String>),
+    Array(usize),
+}
+
+fn container_path(stack: &[Container]) -> Vec<String> {
+    stack
+        .iter()
+        .filter_map(|c| match c {
+            Container::Object(key) => key.clone(),
+            Container::Array(index) => Some(index.to_string()),
+        })
+        .collect()
+}
+
+/// Find every object key and array string in the source.
+/// serde discards duplicate keys and positions, so the source is scanned separately.
+fn scan(source: &str) -> Vec<Entry> {
+    let bytes = source.as_bytes();
+    let mut entries = vec![];
+    let mut stack = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                let text = &source[start + 1..i.min(bytes.len())];
+
+                let mut next = i + 1;
+                while next < bytes.len() && bytes[next].is_ascii_whitespace() {
+                    next += 1;
+                }
+                let is_key = next < bytes.len() && bytes[next] == b':';
+
+                match stack.last_mut() {
+                    Some(Container::Object(key)) if is_key => {
+                        *key = Some(text.to_string());
+                    }
+                    Some(Container::Array(_)) => (),
+                    _ => {
+                        i += 1;
+                        continue;
+                    }
+                }
+
+                entries.push(Entry {
+                    path: container_path(&stack),
+                    offset: start,
+                });
+            }
+            b'{' => stack.push(Container::Object(None)),
+            b'[' => stack.push(Container::Array(0)),
+            b'}' | b']' => {
+                stack.pop();
+            }
+            b',' => {
+                if let Some(Container::Array(index)) = stack.last_mut() {
+                    *index += 1;
+                }
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+
+    entries
+}
+
+#[cfg(test)]
+#[test]
+fn test_scan() {
+    let source = "{\n  \"a\": [\"x\", \"y\"],\n  \"b\": { \"c\": 1, \"c\": 2 }\n}";
+    let paths = scan(source)
+        .into_iter()
+        .map(|e| e.path.join("."))
+        .collect::<Vec<String>>();
+    assert_eq!(paths, vec!["a", "a.0", "a.1", "b", "b.c", "b.c"]);
+}
+
+/// The 1 based line and column of a byte offset.
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Collects diagnostics for a single file.
+struct Validator<'a> {
+    folder: Option<&'a str>,
+    path: &'a Path,
+    source: &'a str,
+    entries: Vec<Entry>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(folder: Option<&'a str>, path: &'a Path, source: &'a str) -> Self {
+        Self {
+            folder,
+            path,
+            source,
+            entries: scan(source),
+            diagnostics: vec![],
+        }
+    }
+
+    /// Report an error at the entry with the given path, or the start of the file.
+    fn report(&mut self, path: &[&str], program: Option<&str>, message: String) {
+        self.report_with(path, program, Severity::Error, message);
+    }
+
+    fn warn(&mut self, path: &[&str], program: Option<&str>, message: String) {
+        self.report_with(path, program, Severity::Warning, message);
+    }
+
+    fn report_with(
+        &mut self,
+        path: &[&str],
+        program: Option<&str>,
+        severity: Severity,
+        message: String,
+    ) {
+        let offset = self
+            .entries
+            .iter()
+            .find(|e| e.path == path)
+            .map_or(0, |e| e.offset);
+        self.report_at(offset, program, severity, message);
+    }
+
+    fn report_at(
+        &mut self,
+        offset: usize,
+        program: Option<&str>,
+        severity: Severity,
+        message: String,
+    ) {
+        let (line, column) = position(self.source, offset);
+        self.diagnostics.push(Diagnostic {
+            path: self.path.to_path_buf(),
+            line,
+            column,
+            severity,
+            message,
+            folder: self.folder.map(|f| f.to_string()),
+            program: program.map(|p| p.to_string()),
+        });
+    }
+
+    /// Warn about keys that appear twice in the same object and keys the schema doesn't know.
+    /// serde keeps the last duplicate and ignores unknown keys, so neither stops a program.
+    fn check_keys(&mut self, schema: &Value) {
+        let entries = self.entries.clone();
+        for (i, entry) in entries.iter().enumerate() {
+            let program = match entry.path.as_slice() {
+                [programs, name, ..] if programs == "programs" => Some(name.as_str()),
+                _ => None,
+            };
+            let key = entry.path.last().cloned().unwrap_or_default();
+
+            if entries[..i].iter().any(|e| e.path == entry.path) {
+                let message = match entry.path.len() {
+                    2 if program.is_some() => format!("Duplicate program '{}'", key),
+                    _ => format!("Duplicate key '{}'", key),
+                };
+                self.report_at(entry.offset, program, Severity::Warning, message);
+            } else if !is_known(schema, &entry.path) {
+                let message = format!("Unknown key '{}'", key);
+                self.report_at(entry.offset, program, Severity::Warning, message);
+            }
+        }
+    }
+}
+
+/// Whether the schema describes the value at the path.
+/// Anything below a schema without properties is accepted.
+fn is_known(schema: &Value, path: &[String]) -> bool {
+    let mut schema = schema;
+    for segment in path.iter() {
+        if let Some(items) = schema.get("items") {
+            schema = items;
+        } else if let Some(property) = schema.get("properties").and_then(|p| p.get(segment)) {
+            schema = property;
+        } else if let Some(additional) = schema.get("additionalProperties") {
+            match additional {
+                Value::Bool(allowed) => return *allowed,
+                _ => schema = additional,
+            }
+        } else {
+            return true;
+        }
+    }
+    true
+}
+
+fn vector3_schema() -> Value {
+    json!({
+        "type": "array",
+        "items": { "type": "number" },
+        "minItems": 3,
+        "maxItems": 3
+    })
+}
+
+fn settings_schema() -> Value {
+    json!({
+        "type": "object",
+        "description": "Default uniform values",
+        "properties": {
+            "audio_feature_smoothing": { "type": "number" },
+            "audio_fft_smoothing": { "type": "number" },
+            "camera_position": vector3_schema(),
+            "camera_target": vector3_schema(),
+            "camera_up": vector3_schema(),
+            "channels": {
+                "type": "array",
+                "description": "Shadertoy channel sources",
+                "items": { "type": "string" },
+                "maxItems": 4
+            },
+            "color_mode": { "type": "integer", "minimum": 0 },
+            "shape_rotation": vector3_schema(),
+            "image1": { "type": "string" },
+            "image2": { "type": "string" },
+            "noise_lacunarity": { "type": "number" },
+            "noise_gain": { "type": "number" },
+            "noise_invert": { "type": "integer" },
+            "noise_mirror": { "type": "integer" },
+            "noise_octaves": { "type": "integer" },
+            "noise_scale_by_prev": { "type": "integer" },
+            "noise_sharpen": { "type": "integer" },
+            "noise_speed": { "type": "number" },
+            "passes": { "type": "integer", "minimum": 0 },
+            "points": {
+                "type": "array",
+                "description": "Names of the points uniforms, set by dragging on the canvas",
+                "items": { "type": "string" },
+                "maxItems": uniforms::points::MAX_POINTS
+            },
+            "video": { "type": "string" },
+            "video_speed": { "type": "number" }
+        },
+        "additionalProperties": false
+    })
+}
+
+fn image_source_schema(kind: &str) -> Value {
+    json!({
+        "type": "object",
+        "properties": { kind: { "type": "string" } },
+        "required": [kind],
+        "additionalProperties": false
+    })
+}
+
+fn program_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "pipeline": {
+                "type": "object",
+                "properties": {
+                    "vert": { "type": "string" },
+                    "frag": { "type": "string" },
+                    "vertex_count": { "type": "integer", "minimum": 0 },
+                    "topology": {
+                        "enum": ["points", "lines", "line_strip", "triangles", "triangle_strip"]
+                    },
+                    "instance_count": { "type": "integer", "minimum": 0 }
+                },
+                "required": ["frag"],
+                "additionalProperties": false
+            },
+            "uniforms": {
+                "type": "array",
+                "items": { "enum": uniforms::UNIFORM_NAMES.to_vec() }
+            },
+            "config": settings_schema(),
+            "isf": { "type": "boolean" },
+            "shadertoy": { "type": "boolean" },
+            "sandbox": { "type": "boolean" },
+            "defines": {
+                "type": "object",
+                "additionalProperties": { "type": "string" }
+            },
+            "images": {
+                "type": "object",
+                "description": "The sources ISF image inputs start with, by input name",
+                "additionalProperties": {
+                    "oneOf": [
+                        { "const": "webcam" },
+                        image_source_schema("image"),
+                        image_source_schema("video"),
+                        image_source_schema("program")
+                    ]
+                }
+            },
+            "chain": {
+                "type": "array",
+                "description": "ISF filters in the folder drawn after this program, in order",
+                "items": { "type": "string" }
+            }
+        },
+        "required": ["pipeline"],
+        "additionalProperties": false
+    })
+}
+
+/// The JSON Schema of a folder's `index.json`.
+pub fn folder_schema() -> Value {
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "rusty-vision folder",
+        "type": "object",
+        "properties": {
+            "$schema": { "type": "string" },
+            "default": { "type": "string", "description": "The program selected when the folder is opened" },
+            "programs": {
+                "type": "object",
+                "additionalProperties": program_schema()
+            }
+        },
+        "required": ["default", "programs"],
+        "additionalProperties": false
+    })
+}
+
+/// The JSON Schema of the root `index.json`.
+pub fn root_schema() -> Value {
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "rusty-vision shader library",
+        "type": "object",
+        "properties": {
+            "$schema": { "type": "string" },
+            "default": { "type": "string", "description": "The folder selected on startup" },
+            "folders": {
+                "type": "array",
+                "items": { "type": "string" },
+                "uniqueItems": true
+            },
+            "prewarm": { "type": "boolean" },
+            "discover": {
+                "type": "boolean",
+                "description": "Add every ISF shader found under the shaders directory"
+            }
+        },
+        "required": ["default", "folders"],
+        "additionalProperties": false
+    })
+}
+
+/// Check the root `index.json` beyond what deserializing it catches.
+pub fn validate_root(path: &Path, source: &str, root: &config::RootConfig) -> Vec<Diagnostic> {
+    let mut validator = Validator::new(None, path, source);
+    validator.check_keys(&root_schema());
+
+    for (i, folder) in root.folders.iter().enumerate() {
+        if root.folders[..i].contains(folder) {
+            let index = i.to_string();
+            validator.warn(
+                &["folders", index.as_str()],
+                None,
+                format!("Duplicate folder '{}'", folder),
+            );
+        }
+    }
+
+    // discovered folders aren't known until the shaders are scanned
+    if !root.discover.unwrap_or(false) && !root.folders.contains(&root.default) {
+        validator.report(
+            &["default"],
+            None,
+            format!("Default folder '{}' is not in folders", root.default),
+        );
+    }
+
+    validator.diagnostics
+}
+
+/// Check a folder's `index.json` beyond what deserializing it catches.
+/// `folder_path` is the directory shader paths are relative to.
+pub fn validate_folder(
+    folder_name: &str,
+    path: &Path,
+    source: &str,
+    folder_path: &Path,
+    folder: &config::FolderConfig,
+) -> Vec<Diagnostic> {
+    let mut validator = Validator::new(Some(folder_name), path, source);
+    validator.check_keys(&folder_schema());
+
+    if !folder.programs.contains_key(&folder.default) {
+        validator.report(
+            &["default"],
+            None,
+            format!("Default program '{}' does not exist", folder.default),
+        );
+    }
+
+    let mut names = folder.programs.keys().collect::<Vec<&String>>();
+    names.sort();
+
+    for name in names {
+        let program = &folder.programs[name];
+        let mut shaders = vec![("frag", &program.pipeline.frag)];
+        if let Some(vert) = &program.pipeline.vert {
+            shaders.push(("vert", vert));
+        }
+
+        for (key, filename) in shaders {
+            if !folder_path.join(filename).exists() {
+                validator.report(
+                    &["programs", name.as_str(), "pipeline", key],
+                    Some(name),
+                    format!("Missing shader file '{}'", filename),
+                );
+            }
+        }
+
+        for (i, filter) in program.chain.iter().flatten().enumerate() {
+            let index = i.to_string();
+            let message = match folder.programs.get(filter) {
+                _ if !program.isf.unwrap_or(false) => {
+                    Some("Only ISF programs can have a chain".to_string())
+                }
+                Some(p) if p.isf.unwrap_or(false) => None,
+                Some(_) => Some(format!("Chain filter '{}' is not an ISF program", filter)),
+                None => Some(format!("Chain filter '{}' does not exist", filter)),
+            };
+            if let Some(message) = message {
+                validator.report(
+                    &["programs", name.as_str(), "chain", index.as_str()],
+                    Some(name),
+                    message,
+                );
+            }
+        }
+
+        for (i, uniform) in program.uniforms.iter().flatten().enumerate() {
+            if !uniforms::UNIFORM_NAMES.contains(&uniform.as_str()) {
+                let index = i.to_string();
+                validator.report(
+                    &["programs", name.as_str(), "uniforms", index.as_str()],
+                    Some(name),
+                    format!(
+                        "Unknown uniforms '{}', expected one of {}",
+                        uniform,
+                        uniforms::UNIFORM_NAMES.join(", ")
+                    ),
+                );
+            }
+        }
+
+        let points = program.config.as_ref().and_then(|c| c.points.as_ref());
+        let subscribed = program.uniforms.iter().flatten().any(|u| u == "points");
+        // extra points are dropped and unsubscribed ones are never bound
+        let message = match points {
+            Some(p) if p.len() > uniforms::points::MAX_POINTS => Some(format!(
+                "At most {} points can be named",
+                uniforms::points::MAX_POINTS
+            )),
+            Some(p) if !p.is_empty() && !subscribed => {
+                Some("Named points need the 'points' uniforms".to_string())
+            }
+            _ => None,
+        };
+        if let Some(message) = message {
+            validator.warn(
+                &["programs", name.as_str(), "config", "points"],
+                Some(name),
+                message,
+            );
+        }
+    }
+
+    validator.diagnostics
+}
+
+#[cfg(test)]
+#[test]
+fn test_severity() {
+    let source = r#"{
+  "default": "a",
+  "programs": {
+    "a": { "pipeline": { "frag": "basic.frag" }, "uniforms": ["general"], "colour": 1 },
+    "b": { "pipeline": { "frag": "missing.frag" }, "uniforms": ["nope"] }
+  }
+}"#;
+    let folder: config::FolderConfig = serde_json::from_str(source).unwrap();
+    let folder_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("shaders/basic");
+    let diagnostics = validate_folder(
+        "test",
+        Path::new("index.json"),
+        source,
+        &folder_path,
+        &folder,
+    );
+
+    let found = diagnostics
+        .iter()
+        .map(|d| {
+            (
+                d.program.clone().unwrap_or_default(),
+                d.severity,
+                d.message.as_str(),
+            )
+        })
+        .collect::<Vec<(String, Severity, &str)>>();
+    assert!(found.contains(&("a".to_string(), Severity::Warning, "Unknown key 'colour'")));
+    assert!(found.contains(&(
+        "b".to_string(),
+        Severity::Error,
+        "Missing shader file 'missing.frag'"
+    )));
+    assert!(found
+        .iter()
+        .any(|(p, s, m)| p == "b" && *s == Severity::Error && m.starts_with("Unknown uniforms")));
+}
+
+#[cfg(test)]
+#[test]
+fn test_exported_schemas() {
+    // regenerate the files from `root_schema` and `folder_schema` when this fails
+    let shaders_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("shaders");
+    for (filename, schema) in [
+        ("index.schema.json", root_schema()),
+        ("folder.schema.json", folder_schema()),
+    ]
+    .iter()
+    {
+        let source = std::fs::read_to_string(shaders_path.join(filename)).unwrap();
+        let exported: Value = serde_json::from_str(&source).unwrap();
+        assert_eq!(&exported, schema);
+    }
+}
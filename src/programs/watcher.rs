@@ -0,0 +1,145 @@
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver};
+use std::time;
+
+/// How long native events are debounced for. Editors often write a file several times per save.
+const NATIVE_DELAY: time::Duration = time::Duration::from_millis(100);
+
+/// How often the directories are scanned when native events are unavailable.
+const POLL_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
+/// What happened to a file since the last frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    Created,
+    Modified,
+    Removed,
+}
+
+/// All file changes since the last frame, with at most one change per path.
+#[derive(Debug, Default)]
+pub struct Changes {
+    pub files: Vec<(PathBuf, Change)>,
+    /// The watcher lost track of events and everything should be reloaded.
+    pub rescan: bool,
+}
+
+impl Changes {
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.files.iter().map(|(path, _)| path)
+    }
+
+    fn record(&mut self, indices: &mut HashMap<PathBuf, usize>, path: PathBuf, change: Change) {
+        let index = match indices.get(&path) {
+            Some(i) => *i,
+            None => {
+                indices.insert(path.clone(), self.files.len());
+                self.files.push((path, change));
+                return;
+            }
+        };
+
+        let previous = self.files[index].1;
+        self.files[index].1 = match (previous, change) {
+            // an atomic save removes the file and then puts a new one in its place
+            (Change::Removed, Change::Created) | (Change::Removed, Change::Modified) => {
+                Change::Modified
+            }
+            (Change::Created, Change::Modified) => Change::Created,
+            (_, Change::Removed) => Change::Removed,
+            (previous, _) => previous,
+        };
+    }
+}
+
+enum WatcherKind {
+    Native(notify::RecommendedWatcher),
+    Poll(notify::PollWatcher),
+}
+
+/// Watches the shader and media directories.
+/// Uses the platform's native file events, falling back to polling where they aren't available.
+pub struct FileWatcher {
+    changes_channel: Receiver<DebouncedEvent>,
+    watcher: WatcherKind,
+}
+
+impl FileWatcher {
+    pub fn new(paths: &[PathBuf]) -> Self {
+        let paths = paths
+            .iter()
+            .filter(|p| p.exists())
+            .cloned()
+            .collect::<Vec<PathBuf>>();
+
+        match Self::native(&paths) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                println!("native file watcher unavailable, polling instead: {}", e);
+                Self::poll(&paths)
+            }
+        }
+    }
+
+    fn native(paths: &[PathBuf]) -> notify::Result<Self> {
+        let (send_channel, changes_channel) = channel();
+        let mut watcher = notify::watcher(send_channel, NATIVE_DELAY)?;
+        for path in paths {
+            watcher.watch(path, RecursiveMode::Recursive)?;
+        }
+
+        Ok(Self {
+            changes_channel,
+            watcher: WatcherKind::Native(watcher),
+        })
+    }
+
+    fn poll(paths: &[PathBuf]) -> Self {
+        let (send_channel, changes_channel) = channel();
+        let mut watcher = notify::PollWatcher::new(send_channel, POLL_INTERVAL).unwrap();
+        for path in paths {
+            if let Err(e) = watcher.watch(path, RecursiveMode::Recursive) {
+                println!("failed to watch {:?}: {}", path, e);
+            }
+        }
+
+        Self {
+            changes_channel,
+            watcher: WatcherKind::Poll(watcher),
+        }
+    }
+
+    pub fn is_native(&self) -> bool {
+        matches!(self.watcher, WatcherKind::Native(_))
+    }
+
+    /// Drain every pending event, coalescing them into one change per path.
+    /// Call once per frame.
+    pub fn changes(&self) -> Changes {
+        let mut changes = Changes::default();
+        let mut indices = HashMap::new();
+
+        for event in self.changes_channel.try_iter() {
+            match event {
+                DebouncedEvent::Create(path) => changes.record(&mut indices, path, Change::Created),
+                DebouncedEvent::Write(path) => changes.record(&mut indices, path, Change::Modified),
+                DebouncedEvent::Remove(path) => changes.record(&mut indices, path, Change::Removed),
+                DebouncedEvent::Rename(from, to) => {
+                    changes.record(&mut indices, from, Change::Removed);
+                    changes.record(&mut indices, to, Change::Created);
+                }
+                DebouncedEvent::Rescan => changes.rescan = true,
+                DebouncedEvent::Error(e, path) => {
+                    println!("file watcher error ({:?}): {}", path, e);
+                }
+                DebouncedEvent::NoticeWrite(_)
+                | DebouncedEvent::NoticeRemove(_)
+                | DebouncedEvent::Chmod(_) => (),
+            }
+        }
+
+        changes
+    }
+}
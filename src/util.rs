@@ -4,6 +4,8 @@ use nannou::prelude::*;
 use path_slash::PathBufExt;
 use std::path::PathBuf;
 
+use crate::app;
+
 // The vertex type that we will use to represent a point on our triangle.
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -46,6 +48,25 @@ pub fn create_pipeline(
     )
 }
 
+/// Create a pipeline without a vertex buffer, for vertex shaders that generate
+/// their positions from `gl_VertexIndex`.
+pub fn create_vertex_pipeline(
+    device: &wgpu::Device,
+    layout_desc: &wgpu::PipelineLayoutDescriptor,
+    vs: &wgpu::ShaderModule,
+    fs: &wgpu::ShaderModule,
+    topology: wgpu::PrimitiveTopology,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(layout_desc);
+    wgpu::RenderPipelineBuilder::from_layout(&pipeline_layout, vs)
+        .fragment_shader(fs)
+        .color_format(Frame::TEXTURE_FORMAT)
+        .sample_count(sample_count)
+        .primitive_topology(topology)
+        .build(device)
+}
+
 // See the `nannou::wgpu::bytes` documentation for why this is necessary.
 pub fn vertices_as_bytes(data: &[Vertex]) -> &[u8] {
     unsafe { wgpu::bytes::from_slice(data) }
@@ -116,8 +137,10 @@ pub fn shaders_path(app: &App) -> PathBuf {
         .join("shaders")
 }
 
-pub fn shaders_path_string(app: &App) -> String {
-    shaders_path(app).into_os_string().into_string().unwrap()
+pub fn media_path(app: &App) -> PathBuf {
+    app.project_path()
+        .expect("failed to locate `project_path`")
+        .join(app::MEDIA_DIR)
 }
 
 pub fn create_app_texture(device: &wgpu::Device, size: Point2, msaa_samples: u32) -> wgpu::Texture {
@@ -145,18 +168,28 @@ pub fn create_texture(
         .build(device)
 }
 
+/// How a texture is declared in a bind group, 32-bit float textures can't be filtered.
+pub fn texture_sample_type(texture: &wgpu::Texture) -> wgpu::TextureSampleType {
+    match texture.format() {
+        wgpu::TextureFormat::R32Float
+        | wgpu::TextureFormat::Rg32Float
+        | wgpu::TextureFormat::Rgba32Float => wgpu::TextureSampleType::Float { filterable: false },
+        _ => texture.sample_type(),
+    }
+}
+
 pub fn create_texture_reshaper(
     device: &wgpu::Device,
     texture: &wgpu::Texture,
     msaa_samples: u32,
 ) -> wgpu::TextureReshaper {
     let texture_view = texture.view().build();
-    let texture_sample_type = texture.sample_type();
+    let texture_sample_type = texture_sample_type(texture);
     let dst_format = Frame::TEXTURE_FORMAT;
     wgpu::TextureReshaper::new(
         device,
         &texture_view,
-        msaa_samples,
+        texture.sample_count(),
         texture_sample_type,
         msaa_samples,
         dst_format,
@@ -195,3 +228,67 @@ pub fn floats_as_byte_vec(data: &[f32]) -> Vec<u8> {
     data.iter().for_each(|f| bytes.extend(float_as_bytes(f)));
     bytes
 }
+
+/// Converts days since the unix epoch to a (year, month, day) civil date.
+/// See http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+#[test]
+fn test_civil_from_days() {
+    assert_eq!(civil_from_days(0), (1970, 1, 1));
+    assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+    assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+}
+
+/// The current UTC date as year, month (1-12), day (1-31) and seconds since midnight.
+pub fn current_date() -> [f32; 4] {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = now.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let seconds = (secs % 86_400) as f32 + now.subsec_micros() as f32 / 1_000_000.0;
+
+    [year as f32, month as f32, day as f32, seconds]
+}
+
+/// A window position, in points from the center, as canvas pixels from the bottom left.
+pub fn window_to_canvas(rect: Rect, canvas_size: Point2, position: Point2) -> Point2 {
+    pt2(
+        (position.x - rect.left()) / rect.w() * canvas_size[0],
+        (position.y - rect.bottom()) / rect.h() * canvas_size[1],
+    )
+}
+
+/// A position in canvas pixels from the bottom left as a window position.
+pub fn canvas_to_window(rect: Rect, canvas_size: Point2, point: Point2) -> Point2 {
+    pt2(
+        rect.left() + point.x / canvas_size[0] * rect.w(),
+        rect.bottom() + point.y / canvas_size[1] * rect.h(),
+    )
+}
+
+#[cfg(test)]
+#[test]
+fn test_window_to_canvas() {
+    // a 960x540 point window drawn at 1920x1080 pixels
+    let rect = Rect::from_w_h(960.0, 540.0);
+    let size = pt2(1920.0, 1080.0);
+
+    assert_eq!(window_to_canvas(rect, size, pt2(0.0, 0.0)), pt2(960.0, 540.0));
+    assert_eq!(window_to_canvas(rect, size, pt2(-480.0, -270.0)), pt2(0.0, 0.0));
+    assert_eq!(window_to_canvas(rect, size, pt2(480.0, 270.0)), pt2(1920.0, 1080.0));
+    assert_eq!(canvas_to_window(rect, size, pt2(480.0, 810.0)), pt2(-240.0, 135.0));
+}